@@ -7,14 +7,16 @@ use rocket_roles::{
     auth::{AuthProvider, AuthError, User, register_auth_provider},
     define_roles, require_role, require_permission
 };
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::sync::RwLock;
 use async_trait::async_trait;
 
 // Define roles and their permissions
 define_roles! {
     "admin" => ["manage_system", "view_users"],
-    "user" => ["view_profile", "edit_profile"]
+    "user" => ["view_profile", "edit_profile"],
+    // Granted to individual users rather than through a role
+    permissions => ["special_access"]
 }
 
 // In-memory auth provider with hardcoded users and tokens
@@ -61,21 +63,21 @@ impl AuthProvider for MemoryAuthProvider {
 // Routes protected by roles
 #[require_role("admin")]
 #[get("/admin")]
-fn admin_only() -> rocket::Either<&'static str, rocket::Response<'static>> {
-    rocket::Either::Left("Welcome, admin!")
+fn admin_only() -> &'static str {
+    "Welcome, admin!"
 }
 
 // Routes protected by permissions
 #[require_permission("edit_profile")]
 #[get("/profile/edit")]
-fn edit_profile() -> rocket::Either<&'static str, rocket::Response<'static>> {
-    rocket::Either::Left("Edit your profile here")
+fn edit_profile() -> &'static str {
+    "Edit your profile here"
 }
 
 #[require_permission("special_access")]
 #[get("/special")]
-fn special_access() -> rocket::Either<&'static str, rocket::Response<'static>> {
-    rocket::Either::Left("This is a special area!")
+fn special_access() -> &'static str {
+    "This is a special area!"
 }
 
 // Public route
@@ -88,7 +90,7 @@ fn index() -> &'static str {
 fn rocket() -> _ {
     // Create and register auth provider
     let auth_provider = MemoryAuthProvider::new();
-    register_auth_provider(auth_provider);
+    register_auth_provider(auth_provider).expect("auth provider registered once");
     
     // Initialize roles
     initialize_roles();
@@ -107,7 +107,7 @@ impl AuthProvider for PostgresAuthProvider {
         .map_err(|e| AuthError::DatabaseError(e.to_string()))?
         .into_iter()
         .map(|r| r.role)
-        .collect();
+        .collect::<Vec<_>>();
         
         // Get direct permissions
         let permissions = sqlx::query!(
@@ -124,12 +124,9 @@ impl AuthProvider for PostgresAuthProvider {
         .map(|p| p.permission)
         .collect::<HashSet<_>>();
         
-        Ok(User {
-            id: user.id.to_string(),
-            username: user.username,
-            roles,
-            permissions,
-        })
+        Ok(User::new(user.id.to_string(), user.username)
+            .with_roles(roles)
+            .with_permissions(permissions))
     }
 }
 
@@ -179,7 +176,7 @@ async fn rocket() -> _ {
         .expect("Failed to connect to database");
     
     // Register auth provider
-    register_auth_provider(auth_provider);
+    register_auth_provider(auth_provider).expect("auth provider registered once");
     
     // Initialize roles
     initialize_roles();
@@ -7,7 +7,6 @@ use rocket_roles::{
     auth::{AuthProvider, AuthError, User, register_auth_provider},
     define_roles, require_role, require_permission
 };
-use std::collections::HashSet;
 use async_trait::async_trait;
 
 // Define roles and their permissions
@@ -56,18 +55,14 @@ impl AuthProvider for RedisAuthProvider {
 // Routes protected by roles
 #[require_role("admin")]
 #[get("/admin/dashboard")]
-fn admin_dashboard() -> rocket::response::Response<'static> {
-    rocket::response::Response::build()
-        .sized_body(std::io::Cursor::new("Welcome to the admin dashboard!"))
-        .finalize()
+fn admin_dashboard() -> &'static str {
+    "Welcome to the admin dashboard!"
+}
+
 // Routes protected by permissions
 #[require_permission("view_orders")]
 #[get("/orders")]
-fn view_orders() -> rocket::response::Response<'static> {
-    rocket::response::Response::build()
-        .sized_body(std::io::Cursor::new("Here are your orders"))
-        .finalize()
-}
+fn view_orders() -> &'static str {
     "Here are your orders"
 }
 
@@ -81,7 +76,7 @@ fn index() -> &'static str {
 fn rocket() -> _ {
     // Create and register auth provider
     let auth_provider = RedisAuthProvider::new();
-    register_auth_provider(auth_provider);
+    register_auth_provider(auth_provider).expect("auth provider registered once");
     
     // Initialize roles
     initialize_roles();
@@ -0,0 +1,106 @@
+//! Checks of role and permission names against those declared with
+//! `define_roles!`
+//!
+//! `define_roles!` emits a `__rocket_roles_declared!` macro listing the
+//! names it declares. In crates with a `check-names` feature, the code
+//! generated by `require_role` / `require_permission` invokes that macro
+//! through `crate::__rocket_roles_declared!`, which hands the referenced
+//! and the declared names to [`check`]. The check therefore depends only on
+//! name resolution, not on the order in which macros are expanded, and a
+//! crate that opts in without `define_roles!` fails to compile instead of
+//! silently skipping it.
+//!
+//! The feature is the calling crate's own, checked through `cfg` in the
+//! generated code. A feature of this crate would be unified across the
+//! whole build, turning the check on for crates that never declared their
+//! names.
+
+use syn::LitStr;
+
+/// Which kind of name is being checked
+#[derive(Clone, Copy)]
+pub enum NameKind {
+    Role,
+    Permission,
+}
+
+impl NameKind {
+    fn label(self) -> &'static str {
+        match self {
+            NameKind::Role => "role",
+            NameKind::Permission => "permission",
+        }
+    }
+}
+
+/// Check that every name was declared
+///
+/// Wildcard grants such as `posts:*` or `*` grant permissions but do not
+/// declare them, so only literal names count; otherwise every typo under a
+/// wildcard would pass.
+pub fn check(kind: NameKind, names: &[LitStr], declared: &[String]) -> syn::Result<()> {
+    for name in names {
+        let value = name.value();
+        let covered = match kind {
+            NameKind::Role => declared.contains(&value),
+            NameKind::Permission => declared.iter().any(|grant| !is_wildcard(grant) && *grant == value),
+        };
+        if covered {
+            continue;
+        }
+        
+        let mut message = format!("{} '{}' is not declared in define_roles!", kind.label(), value);
+        if let Some(suggestion) = closest(&value, declared) {
+            message.push_str(&format!("; did you mean '{}'?", suggestion));
+        }
+        return Err(syn::Error::new(name.span(), message));
+    }
+    
+    Ok(())
+}
+
+/// Whether a permission grant contains a `*` segment
+pub fn is_wildcard(grant: &str) -> bool {
+    grant.split(':').any(|segment| segment == "*")
+}
+
+/// The declared name closest to `name`, if any is close enough to be a typo
+fn closest<'a>(name: &str, known: &'a [String]) -> Option<&'a str> {
+    let threshold = (name.chars().count() / 3).max(1);
+    known
+        .iter()
+        .filter(|candidate| !is_wildcard(candidate))
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= threshold)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+/// Levenshtein distance, counting a swap of two adjacent characters as one
+/// edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    
+    rows[a.len()][b.len()]
+}
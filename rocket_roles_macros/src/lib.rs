@@ -5,22 +5,67 @@
 
 extern crate proc_macro;
 use proc_macro::TokenStream;
-use quote::{quote, format_ident};
-use syn::{parse_macro_input, LitStr, ItemFn, parse::Parse, parse, Token, bracketed, punctuated::Punctuated};
+mod declared;
+mod requirement;
+use declared::NameKind;
+use requirement::Requirement;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned, format_ident};
+use std::collections::{HashMap, HashSet};
+use syn::{parse_macro_input, parse_quote, spanned::Spanned, LitStr, ItemFn, FnArg, Ident, Pat, Type, parse::Parse, Token, bracketed, punctuated::Punctuated};
+
+/// A single role parsed from the define_roles macro
+struct RoleDefinition {
+    name: LitStr,
+    parents: Vec<LitStr>,
+    permissions: Vec<LitStr>,
+}
 
 /// Struct to parse roles and permissions from the define_roles macro
 struct RoleDefinitions {
-    roles: Vec<(String, Vec<String>)>,
+    roles: Vec<RoleDefinition>,
+    /// Permissions declared without a role, e.g. ones granted to users directly
+    extra_permissions: Vec<LitStr>,
 }
 
 impl Parse for RoleDefinitions {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
         let mut roles = Vec::new();
+        let mut extra_permissions = Vec::new();
         
         while !input.is_empty() {
+            // Parse `permissions => [...]`, declaring permissions no role grants
+            if input.peek(Ident) {
+                let keyword: Ident = input.parse()?;
+                if keyword != "permissions" {
+                    return Err(syn::Error::new(keyword.span(), "expected a role name or `permissions`"));
+                }
+                input.parse::<Token![=>]>()?;
+                let content;
+                bracketed!(content in input);
+                extra_permissions.extend(Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?);
+                if input.peek(Token![,]) {
+                    input.parse::<Token![,]>()?;
+                }
+                continue;
+            }
+            
             // Parse role name
             let role_name: LitStr = input.parse()?;
             
+            // Parse optional parent roles: `: "parent", "other_parent"`
+            let mut parents = Vec::new();
+            if input.peek(Token![:]) {
+                input.parse::<Token![:]>()?;
+                loop {
+                    parents.push(input.parse::<LitStr>()?);
+                    if input.peek(Token![=>]) {
+                        break;
+                    }
+                    input.parse::<Token![,]>()?;
+                }
+            }
+            
             // Parse =>
             input.parse::<Token![=>]>()?;
             
@@ -30,10 +75,9 @@ impl Parse for RoleDefinitions {
             
             let permissions = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?
                 .into_iter()
-                .map(|lit| lit.value())
                 .collect();
             
-            roles.push((role_name.value(), permissions));
+            roles.push(RoleDefinition { name: role_name, parents, permissions });
             
             // Parse optional comma
             if input.peek(Token![,]) {
@@ -41,10 +85,175 @@ impl Parse for RoleDefinitions {
             }
         }
         
-        Ok(RoleDefinitions { roles })
+        let definitions = RoleDefinitions { roles, extra_permissions };
+        definitions.validate_hierarchy()?;
+        Ok(definitions)
     }
 }
 
+impl RoleDefinitions {
+    /// Reject parents that were never defined and inheritance cycles
+    fn validate_hierarchy(&self) -> syn::Result<()> {
+        let parents_of: HashMap<String, Vec<String>> = self.roles
+            .iter()
+            .map(|role| (role.name.value(), role.parents.iter().map(LitStr::value).collect()))
+            .collect();
+        
+        for role in &self.roles {
+            for parent in &role.parents {
+                if !parents_of.contains_key(&parent.value()) {
+                    return Err(syn::Error::new(
+                        parent.span(),
+                        format!("unknown parent role '{}'", parent.value()),
+                    ));
+                }
+            }
+        }
+        
+        for role in &self.roles {
+            let name = role.name.value();
+            let mut path = vec![name.as_str()];
+            if let Some(cycle) = find_cycle(&parents_of, &mut path, &mut HashSet::new()) {
+                return Err(syn::Error::new(
+                    role.name.span(),
+                    format!("role inheritance cycle: {}", cycle.join(" -> ")),
+                ));
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+/// Convert a role or permission name such as `edit_post` or `posts:read`
+/// into an enum variant name such as `EditPost` or `PostsRead`
+fn variant_name(name: &LitStr) -> syn::Result<Ident> {
+    let mut variant = String::new();
+    for word in name.value().split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            variant.extend(first.to_uppercase());
+            variant.push_str(chars.as_str());
+        }
+    }
+    
+    match variant.chars().next() {
+        Some(first) if first.is_alphabetic() => Ok(Ident::new(&variant, name.span())),
+        Some(_) => Ok(format_ident!("_{}", variant, span = name.span())),
+        None => Err(syn::Error::new(name.span(), "name must contain at least one letter or digit")),
+    }
+}
+
+/// Generate an enum with one variant per name, convertible to and from the
+/// name as a string
+fn name_enum(enum_name: &Ident, kind: &str, names: &[&LitStr]) -> syn::Result<TokenStream2> {
+    let mut variants: Vec<Ident> = Vec::new();
+    let mut seen: HashMap<String, String> = HashMap::new();
+    for name in names {
+        let variant = variant_name(name)?;
+        if let Some(other) = seen.insert(variant.to_string(), name.value()) {
+            return Err(syn::Error::new(
+                name.span(),
+                format!("{} '{}' and '{}' both map to the enum variant `{}`", kind, other, name.value(), variant),
+            ));
+        }
+        variants.push(variant);
+    }
+    
+    let doc = format!("A {} declared with `define_roles!`", kind);
+    let serde = quote! { rocket_roles::macros::__private::serde };
+    
+    Ok(quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub enum #enum_name {
+            #(#[doc = #names] #variants,)*
+        }
+        
+        impl #enum_name {
+            /// Every declared value, in declaration order
+            pub const ALL: &'static [#enum_name] = &[#(#enum_name::#variants),*];
+            
+            /// The name as written in `define_roles!`
+            pub fn as_str(&self) -> &'static str {
+                match *self {
+                    #(#enum_name::#variants => #names,)*
+                }
+            }
+        }
+        
+        impl std::fmt::Display for #enum_name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+        
+        impl std::str::FromStr for #enum_name {
+            type Err = rocket_roles::macros::ParseNameError;
+            
+            fn from_str(name: &str) -> Result<Self, Self::Err> {
+                match name {
+                    #(#names => Ok(#enum_name::#variants),)*
+                    _ => Err(rocket_roles::macros::ParseNameError::new(#kind, name)),
+                }
+            }
+        }
+        
+        impl AsRef<str> for #enum_name {
+            fn as_ref(&self) -> &str {
+                self.as_str()
+            }
+        }
+        
+        impl From<#enum_name> for String {
+            fn from(value: #enum_name) -> String {
+                value.as_str().to_string()
+            }
+        }
+        
+        impl #serde::Serialize for #enum_name {
+            fn serialize<S: #serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+        
+        impl<'de> #serde::Deserialize<'de> for #enum_name {
+            fn deserialize<D: #serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let name = <String as #serde::Deserialize>::deserialize(deserializer)?;
+                name.parse().map_err(#serde::de::Error::custom)
+            }
+        }
+    })
+}
+
+/// Depth-first search for a path that leads back to its starting role
+fn find_cycle<'a>(
+    parents_of: &'a HashMap<String, Vec<String>>,
+    path: &mut Vec<&'a str>,
+    visited: &mut HashSet<&'a str>,
+) -> Option<Vec<&'a str>> {
+    let current = *path.last()?;
+    for parent in parents_of.get(current).into_iter().flatten() {
+        if parent == path[0] {
+            let mut cycle = path.clone();
+            cycle.push(parent);
+            return Some(cycle);
+        }
+        // Roles already explored from this start cannot lead back to it,
+        // and a cycle that does not pass through the start is reported when
+        // one of its own roles is checked
+        if !visited.insert(parent) {
+            continue;
+        }
+        path.push(parent);
+        if let Some(cycle) = find_cycle(parents_of, path, visited) {
+            return Some(cycle);
+        }
+        path.pop();
+    }
+    None
+}
+
 /// Defines roles and their permissions for the application
 ///
 /// # Example
@@ -59,14 +268,86 @@ impl Parse for RoleDefinitions {
 /// }
 /// ```
 ///
-/// This will generate a function called `initialize_roles` that registers
-/// the defined roles and their permissions with the authentication system.
+/// A role can inherit every permission of one or more parent roles,
+/// transitively, by listing them after a colon:
+///
+/// ```
+/// # use rocket_roles::define_roles;
+/// define_roles! {
+///     "user" => ["view_profile", "edit_profile"],
+///     "moderator": "user" => ["delete_post", "edit_post", "pin_post"],
+///     "admin": "moderator" => ["create_user", "delete_user"]
+/// }
+/// ```
+///
+/// Unknown parent roles and inheritance cycles are rejected at compile time.
+///
+/// Permissions that no role grants, e.g. ones granted to individual users by
+/// the auth provider, can be declared with a `permissions` entry:
+///
+/// ```
+/// # use rocket_roles::define_roles;
+/// define_roles! {
+///     "user" => ["view_profile"],
+///     permissions => ["beta_features"]
+/// }
+/// ```
+///
+/// This will generate a function called `defined_roles` that returns the
+/// roles as a map, suitable for `RocketRoles::new`, and a function called
+/// `initialize_roles` that registers them globally with the authentication
+/// system.
+///
+/// It also generates a `RoleName` and a `PermissionName` enum with one
+/// variant per declared name (`"edit_post"` becomes
+/// `PermissionName::EditPost`). They are named so as not to clash with
+/// `rocket_roles::Role` and `rocket_roles::Permission`. Both
+/// implement `Display`, `FromStr`, `AsRef<str>` and serde's `Serialize` and
+/// `Deserialize`, all using the name as written.
+///
+/// Permissions may use `*` segments as wildcard grants, e.g. `posts:*`.
+/// These are not included in the `PermissionName` enum and do not declare
+/// the names they cover; list those under `permissions => [...]`.
+///
+/// In crates with a `check-names` feature of their own, enabled,
+/// `require_role` and `require_permission` reject names that were not
+/// declared, at compile time. The check goes through a hidden
+/// `__rocket_roles_declared!` macro that `define_roles!` emits and the routes
+/// refer to as `crate::__rocket_roles_declared!`, so `define_roles!` must be
+/// invoked at the crate root, or the macro re-exported there with
+/// `use roles::__rocket_roles_declared;`.
 #[proc_macro]
 pub fn define_roles(input: TokenStream) -> TokenStream {
     let role_defs = parse_macro_input!(input as RoleDefinitions);
     
-    let role_statements = role_defs.roles.iter().map(|(role_name, permissions)| {
-        let perm_statements = permissions.iter().map(|perm| {
+    let role_names: Vec<&LitStr> = role_defs.roles.iter().map(|role| &role.name).collect();
+    let mut permission_names: Vec<&LitStr> = Vec::new();
+    let all_permissions = role_defs.roles.iter().flat_map(|role| &role.permissions).chain(&role_defs.extra_permissions);
+    for permission in all_permissions {
+        if !permission_names.iter().any(|seen| seen.value() == permission.value()) {
+            permission_names.push(permission);
+        }
+    }
+    let enums = name_enum(&format_ident!("RoleName"), "role", &role_names).and_then(|roles| {
+        // Wildcard grants such as `posts:*` are not permissions of their own
+        let concrete: Vec<&LitStr> = permission_names
+            .iter()
+            .copied()
+            .filter(|name| !declared::is_wildcard(&name.value()))
+            .collect();
+        let permissions = name_enum(&format_ident!("PermissionName"), "permission", &concrete)?;
+        Ok(quote! { #roles #permissions })
+    });
+    let enums = match enums {
+        Ok(enums) => enums,
+        Err(error) => return error.into_compile_error().into(),
+    };
+    
+    let role_statements = role_defs.roles.iter().map(|role| {
+        let role_name = role.name.value();
+        let parents = role.parents.iter().map(LitStr::value);
+        let permissions = role.permissions.iter().map(LitStr::value);
+        let perm_statements = permissions.map(|perm| {
             quote! {
                 permissions.insert(#perm.to_string());
             }
@@ -82,116 +363,633 @@ pub fn define_roles(input: TokenStream) -> TokenStream {
                     Role {
                         name: #role_name.to_string(),
                         permissions,
+                        parents: vec![#(#parents.to_string()),*],
                     }
                 );
             }
         }
     });
     
+    // Lists the declared names for the checks generated by the attribute
+    // macros in crates with the `check-names` feature
+    let declared_roles = role_names.iter().map(|name| name.value());
+    let declared_permissions = permission_names.iter().map(|name| name.value());
+    let declarations = quote! {
+        #[doc(hidden)]
+        #[allow(unused_macros)]
+        macro_rules! __rocket_roles_declared {
+            ($kind:ident [$($name:literal),*]) => {
+                rocket_roles::macros::__private::check_declared! {
+                    $kind [$($name),*]
+                    roles [#(#declared_roles),*]
+                    permissions [#(#declared_permissions),*]
+                }
+            };
+        }
+        
+        #[doc(hidden)]
+        #[allow(unused_imports)]
+        pub(crate) use __rocket_roles_declared;
+    };
+    
     let output = quote! {
-        pub fn initialize_roles() {
-            use std::collections::{HashMap, HashSet};
-            use rocket_roles::auth::{Role, register_roles};
+        #enums
+        
+        #declarations
+        
+        pub fn defined_roles() -> std::collections::HashMap<String, rocket_roles::auth::Role> {
+            use std::collections::HashMap;
+            use rocket_roles::auth::Role;
             
             let mut roles = HashMap::new();
             
             #(#role_statements)*
             
-            register_roles(roles);
+            roles
+        }
+        
+        pub fn initialize_roles() {
+            rocket_roles::auth::register_roles(defined_roles());
         }
     };
     
     output.into()
 }
 
-/// Requires a specific role to access the route
+/// Requires a role, or a combination of roles, to access the route
 ///
 /// # Example
 ///
 /// ```
 /// use rocket_roles::require_role;
 /// use rocket::get;
+/// # rocket_roles::define_roles! { "admin" => [] }
 ///
 /// #[require_role("admin")]
 /// #[get("/admin/dashboard")]
 /// fn admin_dashboard() -> &'static str {
 ///     "Welcome to the admin dashboard!"
 /// }
+/// # fn main() {}
+/// ```
+///
+/// The role check runs in a generated request guard, before the handler is
+/// called, so the handler can return any responder. The authenticated user
+/// is bound as `user` inside the handler body. Users without the role are
+/// rejected with 403 and an `AuthRejection::MissingRole`, which the catchers
+/// in `rocket_roles::rejection` can render.
+///
+/// The attribute must be placed above the route attribute, so that it is
+/// expanded first.
+///
+/// Instead of a single role, a boolean expression built from `any(...)`,
+/// `all(...)` and `not(...)` can be given. Expressions nest arbitrarily:
+///
+/// ```
+/// # use rocket::get;
+/// # use rocket_roles::require_role;
+/// # rocket_roles::define_roles! { "admin" => [], "support" => [], "trainee" => [] }
+/// #[require_role(any("admin", all("support", not("trainee"))))]
+/// #[get("/tickets")]
+/// fn tickets() -> &'static str {
+///     "Open tickets"
+/// }
+/// # fn main() {}
+/// ```
+///
+/// Handlers may be `async` and generic. If the handler already takes a
+/// `User` parameter, the guard fills it in; otherwise the binding can be
+/// renamed with `user = name`:
+///
+/// ```
+/// # use rocket::get;
+/// # use rocket_roles::require_role;
+/// # rocket_roles::define_roles! { "admin" => [] }
+/// #[require_role("admin", user = current)]
+/// #[get("/admin/profile")]
+/// async fn profile() -> String {
+///     format!("Signed in as {}", current.username)
+/// }
+/// # fn main() {}
+/// ```
+///
+/// With `tenant = ...`, roles are checked within the tenant named by the
+/// request, using `User::has_role_in`. The tenant id comes from a path
+/// segment (`"<org_id>"`), a header (`header("X-Org-Id")`) or the subdomain
+/// below a base domain (`subdomain("example.com")`). Requests without a
+/// tenant id are rejected with 400:
+///
+/// ```
+/// # use rocket::get;
+/// # use rocket_roles::require_role;
+/// # rocket_roles::define_roles! { "admin" => [] }
+/// #[require_role("admin", tenant = "<org_id>")]
+/// #[get("/orgs/<org_id>/settings")]
+/// fn settings(org_id: &str) -> String {
+///     format!("Settings for {}", org_id)
+/// }
+/// # fn main() {}
+/// ```
+///
+/// With `provider = "name"`, the user is authenticated with the provider
+/// registered under that name rather than the default one. A handler
+/// parameter such as `User<Sso>` selects the provider the same way.
+///
+/// ```
+/// # use rocket::get;
+/// # use rocket_roles::require_role;
+/// # rocket_roles::define_roles! { "admin" => [] }
+/// #[require_role("admin", provider = "sso")]
+/// #[get("/admin")]
+/// fn admin() -> &'static str {
+///     "Internal admin"
+/// }
+/// # fn main() {}
 /// ```
 #[proc_macro_attribute]
 pub fn require_role(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let role = parse_macro_input!(attr as LitStr).value();
+    let args = parse_macro_input!(attr as RequireArgs);
     let input_fn = parse_macro_input!(item as ItemFn);
     
-    let fn_name = &input_fn.sig.ident;
-    let fn_args = &input_fn.sig.inputs;
-    let fn_output = &input_fn.sig.output;
-    let fn_block = &input_fn.block;
-    let fn_vis = &input_fn.vis;
-    let fn_attrs = &input_fn.attrs;
-    
-    let output = quote! {
-        #(#fn_attrs)*
-        #fn_vis fn #fn_name(user: rocket_roles::User, #fn_args) #fn_output {
-            // The user is already authenticated by the FromRequest impl
-            // Now we just need to check if they have the required role
-            if !user.has_role(#role) {
-                return rocket::Response::build()
-                    .status(rocket::http::Status::Forbidden)
-                    .sized_body(None, std::io::Cursor::new(format!("Role '{}' required", #role)))
-                    .finalize();
-            }
-            
-            // If authorized, execute the original function
-            #fn_block
-        }
-    };
-    
-    output.into()
+    guarded_handler(args, RequirementKind::Role, input_fn)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
 
-/// Requires a specific permission to access the route
+/// Requires a permission, or a combination of permissions, to access the route
 ///
 /// # Example
 ///
 /// ```
 /// use rocket_roles::require_permission;
 /// use rocket::get;
+/// # rocket_roles::define_roles! { "editor" => ["view_admin_panel"] }
 ///
 /// #[require_permission("view_admin_panel")]
 /// #[get("/admin/dashboard")]
 /// fn admin_dashboard() -> &'static str {
 ///     "Welcome to the admin dashboard!"
 /// }
+/// # fn main() {}
 /// ```
+///
+/// The same `any(...)`, `all(...)` and `not(...)` expressions accepted by
+/// [`macro@require_role`] can be used to combine permissions:
+///
+/// ```
+/// # use rocket::post;
+/// # use rocket_roles::require_permission;
+/// # rocket_roles::define_roles! { "editor" => ["edit_post", "banned"] }
+/// #[require_permission(all("edit_post", not("banned")))]
+/// #[post("/posts/<id>")]
+/// fn update_post(id: u32) -> &'static str {
+///     "Post updated"
+/// }
+/// # fn main() {}
+/// ```
+///
+/// The `user` and `tenant` options work as for `require_role`; within a
+/// tenant, permissions are checked with `User::has_permission_in`.
 #[proc_macro_attribute]
 pub fn require_permission(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let permission = parse_macro_input!(attr as LitStr).value();
+    let args = parse_macro_input!(attr as RequireArgs);
+    let input_fn = parse_macro_input!(item as ItemFn);
+    
+    guarded_handler(args, RequirementKind::Permission, input_fn)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Requires an OAuth2 scope, or a combination of scopes, to access the route
+///
+/// # Example
+///
+/// ```
+/// use rocket_roles::require_scope;
+/// use rocket::get;
+///
+/// #[require_scope(all("orders:read", "profile"))]
+/// #[get("/orders")]
+/// fn orders() -> &'static str {
+///     "Your orders"
+/// }
+/// ```
+///
+/// Scopes are read from `User::scopes` and compared exactly. Users without
+/// the scopes are rejected with 403, an `AuthRejection::InsufficientScope`
+/// and, as RFC 6750 requires, a
+/// `WWW-Authenticate: Bearer error="insufficient_scope", scope="..."` header
+/// listing the scopes the route asks for. The `user` option works as for
+/// `require_role`.
+#[proc_macro_attribute]
+pub fn require_scope(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as RequireArgs);
     let input_fn = parse_macro_input!(item as ItemFn);
     
+    guarded_handler(args, RequirementKind::Scope, input_fn)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Arguments accepted by `require_role` and `require_permission`:
+/// a requirement expression followed by optional `key = value` options
+struct RequireArgs {
+    requirement: Requirement,
+    user: Option<Ident>,
+    tenant: Option<TokenStream2>,
+    provider: Option<LitStr>,
+}
+
+impl Parse for RequireArgs {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let requirement = input.parse()?;
+        let mut user = None;
+        let mut tenant = None;
+        let mut provider = None;
+        
+        while input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            match key.to_string().as_str() {
+                "user" => user = Some(input.parse()?),
+                "tenant" => tenant = Some(parse_tenant_source(input)?),
+                "provider" => provider = Some(input.parse()?),
+                other => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        format!("unknown option `{}`, expected `user`, `tenant` or `provider`", other),
+                    ));
+                }
+            }
+        }
+        
+        Ok(RequireArgs { requirement, user, tenant, provider })
+    }
+}
+
+/// Parse a tenant source, `"<param>"`, `header("name")` or
+/// `subdomain("base.domain")`, into an expression building a `TenantSource`
+fn parse_tenant_source(input: syn::parse::ParseStream) -> syn::Result<TokenStream2> {
+    if input.peek(LitStr) {
+        let param: LitStr = input.parse()?;
+        let value = param.value();
+        let name = value
+            .strip_prefix('<')
+            .and_then(|value| value.strip_suffix('>'))
+            .filter(|name| !name.is_empty() && !name.ends_with(".."))
+            .ok_or_else(|| syn::Error::new(param.span(), "expected a path segment such as \"<org_id>\""))?;
+        return Ok(quote! { rocket_roles::tenant::TenantSource::param(#name) });
+    }
+    
+    let source: Ident = input.parse()?;
+    let content;
+    syn::parenthesized!(content in input);
+    let argument: LitStr = content.parse()?;
+    match source.to_string().as_str() {
+        "header" => Ok(quote! { rocket_roles::tenant::TenantSource::header(#argument) }),
+        "subdomain" => Ok(quote! { rocket_roles::tenant::TenantSource::subdomain(#argument) }),
+        _ => Err(syn::Error::new(
+            source.span(),
+            "expected \"<param>\", `header(\"name\")` or `subdomain(\"base.domain\")`",
+        )),
+    }
+}
+
+/// Input of `__check_declared!`: the kind and names to check, then the
+/// names declared by `define_roles!`
+struct CheckDeclared {
+    kind: NameKind,
+    names: Vec<LitStr>,
+    roles: Vec<String>,
+    permissions: Vec<String>,
+}
+
+impl Parse for CheckDeclared {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        fn list(input: syn::parse::ParseStream, label: Option<&str>) -> syn::Result<Vec<LitStr>> {
+            if let Some(label) = label {
+                let ident: Ident = input.parse()?;
+                if ident != label {
+                    return Err(syn::Error::new(ident.span(), format!("expected `{}`", label)));
+                }
+            }
+            let content;
+            bracketed!(content in input);
+            let names = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?;
+            Ok(names.into_iter().collect())
+        }
+        
+        let kind: Ident = input.parse()?;
+        let kind = match kind.to_string().as_str() {
+            "role" => NameKind::Role,
+            "permission" => NameKind::Permission,
+            _ => return Err(syn::Error::new(kind.span(), "expected `role` or `permission`")),
+        };
+        let names = list(input, None)?;
+        let values = |names: Vec<LitStr>| names.iter().map(LitStr::value).collect();
+        let roles = values(list(input, Some("roles"))?);
+        let permissions = values(list(input, Some("permissions"))?);
+        Ok(CheckDeclared { kind, names, roles, permissions })
+    }
+}
+
+/// Check names used by a route against those declared by `define_roles!`
+///
+/// Invoked through the `__rocket_roles_declared!` macro that
+/// `define_roles!` emits; not meant to be used directly.
+#[doc(hidden)]
+#[proc_macro]
+pub fn __check_declared(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as CheckDeclared);
+    let declared = match input.kind {
+        NameKind::Role => &input.roles,
+        NameKind::Permission => &input.permissions,
+    };
+    match declared::check(input.kind, &input.names, declared) {
+        Ok(()) => TokenStream::new(),
+        Err(error) => error.into_compile_error().into(),
+    }
+}
+
+/// What a requirement expression is checked against
+#[derive(Clone, Copy)]
+enum RequirementKind {
+    Role,
+    Permission,
+    Scope,
+}
+
+/// Whether a type is `User`, `rocket_roles::User` or `rocket_roles::auth::User`,
+/// with or without a provider argument
+///
+/// Other paths ending in `User`, such as an application's `models::User`,
+/// are left alone. A bare `User` cannot be told apart from an application
+/// type imported under that name, so the generated code asserts that it is
+/// the one from this crate.
+fn is_user_type(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => {
+            let idents: Vec<String> = path.path.segments.iter().map(|segment| segment.ident.to_string()).collect();
+            let idents: Vec<&str> = idents.iter().map(String::as_str).collect();
+            matches!(idents.as_slice(), ["User"] | ["rocket_roles", "User"] | ["rocket_roles", "auth", "User"])
+        }
+        Type::Paren(paren) => is_user_type(&paren.elem),
+        Type::Group(group) => is_user_type(&group.elem),
+        _ => false,
+    }
+}
+
+/// Generate a request guard that checks the requirement, and add it to the
+/// handler's parameters
+///
+/// The check runs before the handler is called, so the handler body and
+/// return type are left untouched. A failed check stashes an
+/// `AuthRejection` and fails with 403, which Rocket routes to the 403
+/// catcher.
+///
+/// The authenticated user is bound inside the handler to, in order of
+/// preference: an existing `User`-typed parameter, which the guard replaces;
+/// the name given with `user = name`; or `user`, unless another parameter
+/// already has that name.
+fn guarded_handler(args: RequireArgs, kind: RequirementKind, mut input_fn: ItemFn) -> syn::Result<TokenStream2> {
+    let prefix = match kind {
+        RequirementKind::Role => "RequireRole",
+        RequirementKind::Permission => "RequirePermission",
+        RequirementKind::Scope => "RequireScope",
+    };
+    // The names are checked against the ones declared by `define_roles!`
+    // in crates that opt in with a `check-names` feature of their own. The
+    // cfg is evaluated in the crate the route is expanded in, so unlike a
+    // feature of this crate it does not spread to others in the build.
+    let names = args.requirement.names();
+    let check = |kind: TokenStream2| quote! {
+        #[allow(unexpected_cfgs)]
+        #[cfg(feature = "check-names")]
+        const _: () = { crate::__rocket_roles_declared!(#kind [#(#names),*]); };
+    };
+    let check_names = match kind {
+        RequirementKind::Role => check(quote! { role }),
+        RequirementKind::Permission => check(quote! { permission }),
+        // Scopes are granted by the authorization server, not declared here
+        RequirementKind::Scope => {
+            if let Some(tenant) = &args.tenant {
+                return Err(syn::Error::new(tenant.span(), "scopes cannot be checked within a tenant"));
+            }
+            quote! {}
+        }
+    };
+    let predicate = args.requirement.to_predicate(&|name| match (kind, &args.tenant) {
+        (RequirementKind::Role, None) => quote! { user.has_role(#name) },
+        (RequirementKind::Permission, None) => quote! { user.has_permission(#name) },
+        (RequirementKind::Role, Some(_)) => quote! { user.has_role_in(&tenant, #name) },
+        (RequirementKind::Permission, Some(_)) => quote! { user.has_permission_in(&tenant, #name) },
+        (RequirementKind::Scope, _) => quote! { user.has_scope(#name) },
+    });
+    let description = args.requirement.describe();
+    let audit_kind = match kind {
+        RequirementKind::Role => quote! { rocket_roles::audit::AuditKind::Role },
+        RequirementKind::Permission => quote! { rocket_roles::audit::AuditKind::Permission },
+        RequirementKind::Scope => quote! { rocket_roles::audit::AuditKind::Scope },
+    };
+    
+    // Checks within a tenant first resolve the tenant id from the request
+    let (resolve_tenant, detail) = match &args.tenant {
+        Some(source) => (
+            quote! {
+                let source = #source;
+                let tenant = match source.resolve(request) {
+                    Some(tenant) => tenant,
+                    None => {
+                        let rejection = rocket_roles::rejection::AuthRejection::MissingTenant(source.to_string());
+                        rocket_roles::audit::record_check(request, #audit_kind, &user, #description, Some(&rejection));
+                        rejection.stash(request);
+                        return Outcome::Error((rejection.status(), rejection));
+                    }
+                };
+            },
+            quote! { format!("{} in tenant '{}'", #description, tenant) },
+        ),
+        None => (quote! {}, quote! { #description.to_string() }),
+    };
+    let rejection = match kind {
+        RequirementKind::Role => quote! { rocket_roles::rejection::AuthRejection::MissingRole(requirement.clone()) },
+        RequirementKind::Permission => quote! { rocket_roles::rejection::AuthRejection::MissingPermission(requirement.clone()) },
+        RequirementKind::Scope => {
+            let scopes = args.requirement.required_names();
+            quote! {
+                rocket_roles::rejection::AuthRejection::InsufficientScope {
+                    requirement: requirement.clone(),
+                    scopes: vec![#(#scopes.to_string()),*],
+                }
+            }
+        }
+    };
+    
     let fn_name = &input_fn.sig.ident;
-    let fn_args = &input_fn.sig.inputs;
-    let fn_output = &input_fn.sig.output;
-    let fn_block = &input_fn.block;
     let fn_vis = &input_fn.vis;
+    // Attributes stacked on one handler each add a guard, so number them by
+    // the guards added by the attributes expanded before
+    let stacked = input_fn.sig.inputs.iter().filter(|arg| {
+        matches!(arg, FnArg::Typed(typed) if matches!(&*typed.pat, Pat::Ident(pat)
+            if pat.ident.to_string().starts_with("__require") && pat.ident.to_string().ends_with("_guard")))
+    }).count();
+    let suffix = if stacked == 0 { String::new() } else { format!("_{}", stacked) };
+    let guard = format_ident!("__{}_{}{}", prefix, fn_name, suffix);
+    let guard_arg = format_ident!("__{}{}_guard", prefix.to_lowercase(), suffix);
+    
+    // Replace an existing `User` parameter with the guard, keeping its
+    // binding and type, which may select a provider as in `User<Sso>`
+    let mut binding: Option<Pat> = None;
+    let mut user_ty: Type = parse_quote! { rocket_roles::User };
+    let mut assert_user = quote! {};
+    for arg in input_fn.sig.inputs.iter_mut() {
+        if let FnArg::Typed(typed) = arg {
+            if is_user_type(&typed.ty) {
+                binding = Some((*typed.pat).clone());
+                user_ty = (*typed.ty).clone();
+                // Reject an application type that is also named `User`
+                assert_user = quote_spanned! { user_ty.span() =>
+                    const _: fn() = || {
+                        fn assert_user<T: rocket_roles::macros::__private::RocketRolesUser>() {}
+                        assert_user::<#user_ty>();
+                    };
+                };
+                *typed.pat = parse_quote! { #guard_arg };
+                *typed.ty = parse_quote! { #guard };
+                break;
+            }
+        }
+    }
+    
+    match (&binding, &args.user) {
+        (Some(existing), Some(name)) => {
+            let matches = matches!(existing, Pat::Ident(pat) if pat.ident == *name);
+            if !matches {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "`user` names a binding, but the handler already takes a `User` parameter with another name",
+                ));
+            }
+        }
+        (Some(_), None) => {}
+        (None, name) => {
+            let taken = |wanted: &Ident| input_fn.sig.inputs.iter().any(|arg| {
+                matches!(arg, FnArg::Typed(typed) if matches!(&*typed.pat, Pat::Ident(pat) if pat.ident == *wanted))
+            });
+            match name {
+                Some(name) if taken(name) => {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        format!("the handler already has a parameter named `{}`", name),
+                    ));
+                }
+                Some(name) => binding = Some(parse_quote! { #name }),
+                None if taken(&format_ident!("user")) => {}
+                None => binding = Some(parse_quote! { user }),
+            }
+            input_fn.sig.inputs.insert(0, parse_quote! { #guard_arg: #guard });
+        }
+    }
+    
+    // Authenticate with the named provider, or through the guard of the
+    // user type
+    let authenticate = match &args.provider {
+        Some(name) => {
+            let selects_provider = matches!(&user_ty, Type::Path(path) if path.path.segments.last()
+                .is_some_and(|segment| !segment.arguments.is_empty()));
+            if selects_provider {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "the handler's `User<..>` parameter already selects a provider; remove the `provider` option",
+                ));
+            }
+            quote! { rocket_roles::auth::authenticate_request(request, Some(#name)).await }
+        }
+        None => quote! { request.guard::<#user_ty>().await },
+    };
+    
+    let bind_user = binding.map(|pat| quote! {
+        #[allow(unused_variables)]
+        let #pat = #guard_arg.0;
+    });
     let fn_attrs = &input_fn.attrs;
+    let fn_sig = &input_fn.sig;
+    let fn_stmts = &input_fn.block.stmts;
     
-    let output = quote! {
-        #(#fn_attrs)*
-        #fn_vis fn #fn_name(user: rocket_roles::User, #fn_args) #fn_output {
-            // The user is already authenticated by the FromRequest impl
-            // Now we just need to check if they have the required permission
-            if !user.has_permission(#permission) {
-                return rocket::Response::build()
-                    .status(rocket::http::Status::Forbidden)
-                    .sized_body(None, std::io::Cursor::new(format!("Permission '{}' required", #permission)))
-                    .finalize();
+    Ok(quote! {
+        #check_names
+        #assert_user
+        
+        #[doc(hidden)]
+        #[allow(non_camel_case_types)]
+        #fn_vis struct #guard(#user_ty);
+        
+        #[rocket::async_trait]
+        impl<'r> rocket::request::FromRequest<'r> for #guard {
+            type Error = rocket_roles::rejection::AuthRejection;
+            
+            async fn from_request(
+                request: &'r rocket::request::Request<'_>,
+            ) -> rocket::request::Outcome<Self, Self::Error> {
+                use rocket::request::Outcome;
+                
+                // Authenticate first
+                let user = match #authenticate {
+                    Outcome::Success(user) => user,
+                    Outcome::Error(error) => return Outcome::Error(error),
+                    Outcome::Forward(status) => return Outcome::Forward(status),
+                };
+                
+                #resolve_tenant
+                
+                // Then check if they satisfy the requirement
+                let requirement: String = #detail;
+                let allowed = rocket_roles::telemetry::check(#audit_kind, &user, &requirement, || #predicate);
+                if allowed {
+                    rocket_roles::audit::record_check(request, #audit_kind, &user, &requirement, None);
+                    Outcome::Success(#guard(user))
+                } else {
+                    let rejection = #rejection;
+                    rocket_roles::audit::record_check(request, #audit_kind, &user, &requirement, Some(&rejection));
+                    rejection.stash(request);
+                    Outcome::Error((rejection.status(), rejection))
+                }
             }
+        }
+        
+        #(#fn_attrs)*
+        #fn_vis #fn_sig {
+            #bind_user
             
-            // If authorized, execute the original function
-            #fn_block
+            #(#fn_stmts)*
         }
-    };
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RoleDefinitions;
     
-    output.into()
-}
\ No newline at end of file
+    // Test that shared ancestors are explored once, so diamond hierarchies
+    // are checked in polynomial time
+    #[test]
+    fn test_diamond_hierarchy() {
+        let depth = 40;
+        let mut input = String::from(r#""a0" => [], "b0" => [],"#);
+        for level in 1..=depth {
+            let parents = format!(r#""a{0}", "b{0}""#, level - 1);
+            input.push_str(&format!(r#""a{0}": {1} => [], "b{0}": {1} => [],"#, level, parents));
+        }
+        assert!(syn::parse_str::<RoleDefinitions>(&input).is_ok());
+        
+        input.push_str(&format!(r#""c": "a{}" => [],"#, depth));
+        let input = input.replacen(r#""a0" => []"#, r#""a0": "c" => []"#, 1);
+        let error = syn::parse_str::<RoleDefinitions>(&input).err().expect("cycle is reported");
+        assert!(error.to_string().starts_with("role inheritance cycle: a0 -> c -> a40 -> a39"));
+    }
+}
@@ -0,0 +1,195 @@
+//! Boolean requirement expressions accepted by `require_role` and `require_permission`
+//!
+//! A requirement is either a single string literal or a combinator:
+//!
+//! - `any(a, b, ...)` - at least one of the inner requirements holds
+//! - `all(a, b, ...)` - every inner requirement holds
+//! - `not(a)` - the inner requirement does not hold
+//!
+//! Combinators nest arbitrarily, e.g. `all("edit_post", not("banned"))`.
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parenthesized, parse::{Parse, ParseStream}, punctuated::Punctuated, Ident, LitStr, Token};
+
+/// A parsed requirement expression
+pub enum Requirement {
+    /// A single role or permission name
+    Name(LitStr),
+    /// At least one of the inner requirements must hold
+    Any(Vec<Requirement>),
+    /// All of the inner requirements must hold
+    All(Vec<Requirement>),
+    /// The inner requirement must not hold
+    Not(Box<Requirement>),
+}
+
+impl Parse for Requirement {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitStr) {
+            return Ok(Requirement::Name(input.parse()?));
+        }
+        
+        let combinator: Ident = input.parse().map_err(|e| {
+            syn::Error::new(e.span(), "expected a string literal or one of `any(...)`, `all(...)`, `not(...)`")
+        })?;
+        if !input.peek(syn::token::Paren) {
+            return Err(syn::Error::new(
+                combinator.span(),
+                format!("expected a string literal such as \"{}\", or one of `any(...)`, `all(...)`, `not(...)`", combinator),
+            ));
+        }
+        
+        let content;
+        parenthesized!(content in input);
+        
+        match combinator.to_string().as_str() {
+            "any" | "all" => {
+                let inner: Vec<Requirement> = Punctuated::<Requirement, Token![,]>::parse_terminated(&content)?
+                    .into_iter()
+                    .collect();
+                if inner.is_empty() {
+                    return Err(syn::Error::new(
+                        combinator.span(),
+                        format!("`{}` requires at least one argument", combinator),
+                    ));
+                }
+                if combinator == "any" {
+                    Ok(Requirement::Any(inner))
+                } else {
+                    Ok(Requirement::All(inner))
+                }
+            }
+            "not" => {
+                let inner: Requirement = content.parse()?;
+                if !content.is_empty() {
+                    return Err(content.error("`not` takes exactly one argument"));
+                }
+                Ok(Requirement::Not(Box::new(inner)))
+            }
+            other => Err(syn::Error::new(
+                combinator.span(),
+                format!("unknown combinator `{}`, expected `any`, `all` or `not`", other),
+            )),
+        }
+    }
+}
+
+impl Requirement {
+    /// Generate a boolean expression that evaluates this requirement,
+    /// using `check` to generate the test of a single name
+    pub fn to_predicate(&self, check: &dyn Fn(&LitStr) -> TokenStream2) -> TokenStream2 {
+        match self {
+            Requirement::Name(name) => check(name),
+            Requirement::Any(inner) => {
+                let inner = inner.iter().map(|r| r.to_predicate(check));
+                quote! { (#(#inner)||*) }
+            }
+            Requirement::All(inner) => {
+                let inner = inner.iter().map(|r| r.to_predicate(check));
+                quote! { (#(#inner)&&*) }
+            }
+            Requirement::Not(inner) => {
+                let inner = inner.to_predicate(check);
+                quote! { (!#inner) }
+            }
+        }
+    }
+    
+    /// Every name referenced by this requirement
+    pub fn names(&self) -> Vec<&LitStr> {
+        match self {
+            Requirement::Name(name) => vec![name],
+            Requirement::Any(inner) | Requirement::All(inner) => inner.iter().flat_map(Requirement::names).collect(),
+            Requirement::Not(inner) => inner.names(),
+        }
+    }
+    
+    /// The names the requirement asks for, leaving out negated ones
+    pub fn required_names(&self) -> Vec<&LitStr> {
+        match self {
+            Requirement::Name(name) => vec![name],
+            Requirement::Any(inner) | Requirement::All(inner) => {
+                inner.iter().flat_map(Requirement::required_names).collect()
+            }
+            Requirement::Not(_) => Vec::new(),
+        }
+    }
+    
+    /// Human readable form used in rejection messages
+    pub fn describe(&self) -> String {
+        match self {
+            Requirement::Name(name) => format!("'{}'", name.value()),
+            Requirement::Any(inner) => format!("any({})", describe_all(inner)),
+            Requirement::All(inner) => format!("all({})", describe_all(inner)),
+            Requirement::Not(inner) => format!("not({})", inner.describe()),
+        }
+    }
+}
+
+fn describe_all(requirements: &[Requirement]) -> String {
+    requirements
+        .iter()
+        .map(Requirement::describe)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Requirement;
+    use quote::quote;
+    
+    fn parse(input: &str) -> syn::Result<Requirement> {
+        syn::parse_str(input)
+    }
+    
+    fn error(input: &str) -> String {
+        match parse(input) {
+            Ok(requirement) => panic!("`{}` parsed as {}", input, requirement.describe()),
+            Err(error) => error.to_string(),
+        }
+    }
+    
+    fn values(names: Vec<&syn::LitStr>) -> Vec<String> {
+        names.into_iter().map(syn::LitStr::value).collect()
+    }
+    
+    // Test parsing nested combinators
+    #[test]
+    fn test_nesting() {
+        let requirement = parse(r#"any("admin", all("support", not("trainee")), not(any("banned")))"#).unwrap();
+        assert_eq!(
+            requirement.describe(),
+            "any('admin', all('support', not('trainee')), not(any('banned')))"
+        );
+        assert_eq!(values(requirement.names()), ["admin", "support", "trainee", "banned"]);
+        assert_eq!(values(requirement.required_names()), ["admin", "support"]);
+        
+        let predicate = requirement.to_predicate(&|name| quote! { has(#name) });
+        let expected = quote! { (has("admin") || (has("support") && (!has("trainee"))) || (!(has("banned")))) };
+        assert_eq!(predicate.to_string(), expected.to_string());
+        
+        assert_eq!(parse(r#""admin""#).unwrap().describe(), "'admin'");
+        assert_eq!(parse(r#"all("a", "b",)"#).unwrap().describe(), "all('a', 'b')");
+    }
+    
+    // Test that combinators reject the wrong number of arguments
+    #[test]
+    fn test_arity() {
+        assert_eq!(error("any()"), "`any` requires at least one argument");
+        assert_eq!(error("all()"), "`all` requires at least one argument");
+        assert_eq!(error(r#"not("a", "b")"#), "`not` takes exactly one argument");
+        assert_eq!(error("not()"), "expected a string literal or one of `any(...)`, `all(...)`, `not(...)`");
+    }
+    
+    // Test that anything else is rejected
+    #[test]
+    fn test_bad_tokens() {
+        assert_eq!(error(r#"either("a", "b")"#), "unknown combinator `either`, expected `any`, `all` or `not`");
+        assert_eq!(error("42"), "expected a string literal or one of `any(...)`, `all(...)`, `not(...)`");
+        assert_eq!(error("admin"), r#"expected a string literal such as "admin", or one of `any(...)`, `all(...)`, `not(...)`"#);
+        assert_eq!(error(r#"any("a" "b")"#), "expected `,`");
+        assert_eq!(error(r#"any["a"]"#), r#"expected a string literal such as "any", or one of `any(...)`, `all(...)`, `not(...)`"#);
+    }
+}
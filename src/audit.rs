@@ -0,0 +1,460 @@
+//! Audit log of authentication and authorization decisions
+//!
+//! Every decision made by the [`User`](crate::auth::User) guard and by the
+//! checks generated by `#[require_role]`, `#[require_permission]` and
+//! `#[require_scope]` is reported as an [`AuditEvent`] to the sinks
+//! registered with the [`RocketRoles`] fairing: who made the request, which
+//! route, what was required, whether it was allowed and, if not, why.
+//!
+//! ```rust,ignore
+//! use rocket_roles::audit::JsonLinesSink;
+//!
+//! let sink = JsonLinesSink::new("/var/log/app/audit.jsonl")?
+//!     .with_max_bytes(10 * 1024 * 1024)
+//!     .with_max_files(5);
+//!
+//! rocket::build()
+//!     .attach(RocketRoles::new(MyAuthProvider::new(), roles()).with_audit_sink(sink))
+//! ```
+//!
+//! Authentication is recorded once per request, however many guards ask for
+//! the user. A request without a credential is recorded as
+//! [`Anonymous`](Decision::Anonymous): routes that accept anonymous requests
+//! through [`MaybeUser`](crate::auth::MaybeUser) proceed, routes that require
+//! a user answer 401.
+//!
+//! Events are only recorded in applications that attach the fairing; with
+//! a provider registered through
+//! [`register_auth_provider`](crate::auth::register_auth_provider) alone,
+//! nothing is audited.
+
+use crate::auth::User;
+use crate::fairing::RocketRoles;
+use crate::rejection::AuthRejection;
+use rocket::request::Request;
+use serde::{Serialize, Serializer};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default number of rotated files kept by [`JsonLinesSink`]
+const DEFAULT_MAX_FILES: usize = 5;
+
+/// What was decided
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditKind {
+    /// Whether the request carries a valid credential
+    Authentication,
+    /// A `#[require_role]` check
+    Role,
+    /// A `#[require_permission]` check
+    Permission,
+    /// A `#[require_scope]` check
+    Scope,
+}
+
+impl AuditKind {
+    /// The kind's name, e.g. `role`
+    pub fn name(&self) -> &'static str {
+        match self {
+            AuditKind::Authentication => "authentication",
+            AuditKind::Role => "role",
+            AuditKind::Permission => "permission",
+            AuditKind::Scope => "scope",
+        }
+    }
+}
+
+/// The outcome of a decision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    /// The request may proceed
+    Allow,
+    /// The request was rejected
+    Deny,
+    /// The request carries no credential, which only routes that require a
+    /// user reject
+    Anonymous,
+}
+
+/// A single authentication or authorization decision
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEvent {
+    /// When the decision was made, serialized as milliseconds since the
+    /// Unix epoch
+    #[serde(serialize_with = "unix_millis")]
+    pub timestamp: SystemTime,
+    /// What was decided
+    pub kind: AuditKind,
+    /// The outcome
+    pub decision: Decision,
+    /// Id of the authenticated user, if any
+    pub user_id: Option<String>,
+    /// Username of the authenticated user, if any
+    pub username: Option<String>,
+    /// HTTP method of the request
+    pub method: String,
+    /// Path of the request
+    ///
+    /// The query is left out, since it may carry credentials such as an
+    /// `access_token`.
+    pub uri: String,
+    /// Name of the matched route, usually the handler's name
+    pub route: Option<String>,
+    /// The requirement that was checked, e.g. `'admin' in tenant 'acme'`
+    pub requirement: Option<String>,
+    /// Machine-readable reason for a denial, e.g. `missing_role`
+    pub code: Option<&'static str>,
+    /// Human-readable reason for a denial
+    pub reason: Option<String>,
+}
+
+impl AuditEvent {
+    /// Describe a decision about the given request
+    ///
+    /// A missing credential is not a denial by itself: for authentication
+    /// it is recorded as [`Decision::Anonymous`], without a reason.
+    pub fn new(request: &Request<'_>, kind: AuditKind, user: Option<&User>, rejection: Option<&AuthRejection>) -> Self {
+        let (decision, denial) = match rejection {
+            None => (Decision::Allow, None),
+            Some(AuthRejection::MissingCredentials) if kind == AuditKind::Authentication => (Decision::Anonymous, None),
+            Some(rejection) => (Decision::Deny, Some(rejection)),
+        };
+        Self {
+            timestamp: SystemTime::now(),
+            kind,
+            decision,
+            user_id: user.map(|user| user.id.clone()),
+            username: user.map(|user| user.username.clone()),
+            method: request.method().to_string(),
+            uri: request.uri().path().to_string(),
+            route: request.route().and_then(|route| route.name.as_ref()).map(|name| name.to_string()),
+            requirement: None,
+            code: denial.map(AuthRejection::code),
+            reason: denial.map(AuthRejection::to_string),
+        }
+    }
+
+    /// Record the requirement that was checked
+    pub fn with_requirement(mut self, requirement: impl Into<String>) -> Self {
+        self.requirement = Some(requirement.into());
+        self
+    }
+}
+
+fn unix_millis<S: Serializer>(timestamp: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+    let millis = timestamp.duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+    serializer.serialize_u64(millis as u64)
+}
+
+/// The `AuditSink` trait is implemented by destinations for audit events
+///
+/// Sinks are called from request guards, so `record` should be quick and
+/// must not fail; report write errors some other way.
+pub trait AuditSink: Send + Sync + 'static {
+    /// Record a decision
+    fn record(&self, event: &AuditEvent);
+}
+
+impl<S: AuditSink + ?Sized> AuditSink for Arc<S> {
+    fn record(&self, event: &AuditEvent) {
+        (**self).record(event)
+    }
+}
+
+/// Report an event to the sinks registered with the fairing
+pub(crate) fn record(request: &Request<'_>, event: impl FnOnce() -> AuditEvent) {
+    let Some(state) = request.rocket().state::<RocketRoles>() else {
+        return;
+    };
+    if state.audit_sinks.is_empty() {
+        return;
+    }
+
+    let event = event();
+    for sink in state.audit_sinks.iter() {
+        sink.record(&event);
+    }
+}
+
+/// Report the outcome of an attribute macro's check
+///
+/// Called by the code generated by the attribute macros, not meant to be
+/// used directly.
+#[doc(hidden)]
+pub fn record_check<P>(
+    request: &Request<'_>,
+    kind: AuditKind,
+    user: &User<P>,
+    requirement: &str,
+    rejection: Option<&AuthRejection>,
+) {
+    record(request, || AuditEvent {
+        user_id: Some(user.id.clone()),
+        username: Some(user.username.clone()),
+        ..AuditEvent::new(request, kind, None, rejection).with_requirement(requirement)
+    });
+}
+
+/// Sink that keeps events in memory, for tests
+///
+/// Clones share the same events, so keep one to inspect what the
+/// application recorded.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySink {
+    events: Arc<Mutex<Vec<AuditEvent>>>,
+}
+
+impl MemorySink {
+    /// Create an empty sink
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The events recorded so far
+    pub fn events(&self) -> Vec<AuditEvent> {
+        self.events.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Forget all recorded events
+    pub fn clear(&self) {
+        self.events.lock().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+}
+
+impl AuditSink for MemorySink {
+    fn record(&self, event: &AuditEvent) {
+        self.events.lock().unwrap_or_else(PoisonError::into_inner).push(event.clone());
+    }
+}
+
+/// Sink that emits each event as a `tracing` event with the target
+/// `rocket_roles::audit`
+///
+/// Allowed requests are logged at `INFO` and denied ones at `WARN`.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingSink;
+
+#[cfg(feature = "tracing")]
+impl AuditSink for TracingSink {
+    fn record(&self, event: &AuditEvent) {
+        macro_rules! emit {
+            ($level:expr) => {
+                tracing::event!(
+                    target: "rocket_roles::audit",
+                    $level,
+                    kind = ?event.kind,
+                    decision = ?event.decision,
+                    user_id = event.user_id.as_deref(),
+                    method = %event.method,
+                    uri = %event.uri,
+                    route = event.route.as_deref(),
+                    requirement = event.requirement.as_deref(),
+                    code = event.code,
+                    reason = event.reason.as_deref(),
+                    "authorization decision"
+                )
+            };
+        }
+
+        match event.decision {
+            Decision::Allow | Decision::Anonymous => emit!(tracing::Level::INFO),
+            Decision::Deny => emit!(tracing::Level::WARN),
+        }
+    }
+}
+
+/// Sink that appends events to a file, one JSON object per line
+///
+/// With a size limit, the file is rotated before it would grow past the
+/// limit: `audit.jsonl` becomes `audit.jsonl.1`, `audit.jsonl.1` becomes
+/// `audit.jsonl.2` and so on, and the oldest file beyond the configured
+/// number is deleted.
+///
+/// Events are written by a background thread, so recording never blocks
+/// the async executor on file I/O. Use [`flush`](Self::flush) to wait for
+/// pending events; dropping the sink also waits for them.
+pub struct JsonLinesSink {
+    path: PathBuf,
+    /// The writer, until the first event hands it to the background thread
+    pending: Mutex<Option<Writer>>,
+    sender: OnceLock<Sender<Message>>,
+}
+
+/// Work for the background thread
+enum Message {
+    Line(String),
+    Flush(Sender<()>),
+}
+
+/// Writes lines to the current file and rotates it
+struct Writer {
+    path: PathBuf,
+    max_bytes: Option<u64>,
+    max_files: usize,
+    current: OpenFile,
+}
+
+/// The file currently written to and its size
+struct OpenFile {
+    file: File,
+    size: u64,
+}
+
+impl JsonLinesSink {
+    /// Append to the file at `path`, creating it if needed
+    pub fn new(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let current = OpenFile::open(&path)?;
+        let writer = Writer {
+            path: path.clone(),
+            max_bytes: None,
+            max_files: DEFAULT_MAX_FILES,
+            current,
+        };
+        Ok(Self {
+            path,
+            pending: Mutex::new(Some(writer)),
+            sender: OnceLock::new(),
+        })
+    }
+
+    /// Rotate the file before it grows past the given size
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        if let Some(writer) = self.pending.get_mut().unwrap_or_else(PoisonError::into_inner) {
+            writer.max_bytes = Some(max_bytes);
+        }
+        self
+    }
+
+    /// Keep at most this many rotated files, 5 by default
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        if let Some(writer) = self.pending.get_mut().unwrap_or_else(PoisonError::into_inner) {
+            writer.max_files = max_files;
+        }
+        self
+    }
+
+    /// The path of the current file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Wait until every event recorded so far has been written
+    pub fn flush(&self) {
+        let Some(sender) = self.sender.get() else {
+            return;
+        };
+        let (done, wait) = mpsc::channel();
+        if sender.send(Message::Flush(done)).is_ok() {
+            let _ = wait.recv();
+        }
+    }
+
+    /// The channel to the background thread, starting it on first use
+    fn sender(&self) -> &Sender<Message> {
+        self.sender.get_or_init(|| {
+            let (sender, receiver) = mpsc::channel();
+            let writer = self.pending.lock().unwrap_or_else(PoisonError::into_inner).take();
+            if let Some(mut writer) = writer {
+                std::thread::spawn(move || {
+                    for message in receiver {
+                        match message {
+                            Message::Line(line) => {
+                                if let Err(e) = writer.write(&line) {
+                                    rocket::error!("Failed to write audit event to {}: {}", writer.path.display(), e);
+                                }
+                            }
+                            Message::Flush(done) => {
+                                let _ = done.send(());
+                            }
+                        }
+                    }
+                });
+            }
+            sender
+        })
+    }
+}
+
+impl Writer {
+    /// Path of the `index`th rotated file
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        path.into()
+    }
+
+    /// Shift the rotated files up by one and start a new file
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.max_files == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            let oldest = self.rotated_path(self.max_files);
+            if oldest.exists() {
+                std::fs::remove_file(&oldest)?;
+            }
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    std::fs::rename(&from, self.rotated_path(index + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        self.current = OpenFile::open(&self.path)?;
+        Ok(())
+    }
+
+    fn write(&mut self, line: &str) -> io::Result<()> {
+        if let Some(max_bytes) = self.max_bytes {
+            if self.current.size > 0 && self.current.size + line.len() as u64 > max_bytes {
+                self.rotate()?;
+            }
+        }
+        self.current.file.write_all(line.as_bytes())?;
+        self.current.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+impl OpenFile {
+    fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { file, size })
+    }
+}
+
+impl AuditSink for JsonLinesSink {
+    fn record(&self, event: &AuditEvent) {
+        match rocket::serde::json::to_string(event) {
+            Ok(mut line) => {
+                line.push('\n');
+                let _ = self.sender().send(Message::Line(line));
+            }
+            Err(e) => rocket::error!("Failed to serialize audit event: {}", e),
+        }
+    }
+}
+
+impl Drop for JsonLinesSink {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+impl std::fmt::Debug for JsonLinesSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JsonLinesSink")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
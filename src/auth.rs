@@ -1,27 +1,143 @@
 //! Authentication and authorization core types and traits
 
+use crate::audit::{self, AuditEvent, AuditKind};
+use crate::extract::ExtractorChain;
+use crate::fairing::RocketRoles;
+use crate::permission::grant_matches;
+use crate::rejection::AuthRejection;
+use crate::registry::RoleRegistry;
+use crate::telemetry;
 use async_trait::async_trait;
 use once_cell::sync::OnceCell;
+use rocket::http::Status;
 use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, PoisonError, RwLock, RwLockReadGuard};
+use std::time::Duration;
 
 /// Error type for authentication operations
-#[derive(Debug)]
+///
+/// New variants may be added in minor releases, so matches outside this
+/// crate need a wildcard arm.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum AuthError {
     /// The authentication token is invalid
     InvalidToken(String),
+    /// The authentication token was valid but has expired
+    Expired,
+    /// The authentication token was revoked, e.g. by logging out
+    Revoked,
+    /// The account exists but is locked, e.g. after too many failed logins
+    AccountLocked,
+    /// The account exists but has been disabled
+    AccountDisabled,
+    /// Too many authentication attempts; the client may retry after the
+    /// given time, if known
+    RateLimited {
+        /// How long the client should wait before retrying
+        retry_after: Option<Duration>,
+    },
+    /// The identity provider or user store could not be reached
+    ProviderUnavailable {
+        /// What failed
+        message: String,
+        /// The underlying error, if any
+        source: Option<Arc<dyn std::error::Error + Send + Sync>>,
+    },
     /// Database connection error
     DatabaseError(String),
     /// User not found
     UserNotFound,
-    /// Generic error
+    /// Any other failure to authenticate the token. Reported as 401, like
+    /// every error was before the more specific variants existed; use
+    /// [`ProviderUnavailable`](Self::ProviderUnavailable) for backend
+    /// failures.
     Other(String),
 }
 
+impl AuthError {
+    /// A provider failure caused by another error, such as a network or
+    /// database error
+    pub fn unavailable(message: impl Into<String>, source: impl std::error::Error + Send + Sync + 'static) -> Self {
+        AuthError::ProviderUnavailable {
+            message: message.into(),
+            source: Some(Arc::new(source)),
+        }
+    }
+
+    /// The HTTP status a request failing with this error should get
+    ///
+    /// Problems with the credential are 401, problems with the account are
+    /// 403, rate limiting is 429, and backend failures are 5xx so clients
+    /// are not told to log in again during an outage.
+    pub fn status(&self) -> Status {
+        match self {
+            AuthError::InvalidToken(_)
+            | AuthError::Expired
+            | AuthError::Revoked
+            | AuthError::UserNotFound
+            | AuthError::Other(_) => Status::Unauthorized,
+            AuthError::AccountLocked | AuthError::AccountDisabled => Status::Forbidden,
+            AuthError::RateLimited { .. } => Status::TooManyRequests,
+            AuthError::ProviderUnavailable { .. } | AuthError::DatabaseError(_) => Status::ServiceUnavailable,
+        }
+    }
+
+    /// Whether the error is the client's fault (4xx) rather than the
+    /// server's (5xx)
+    pub fn is_client_error(&self) -> bool {
+        self.status().class().is_client_error()
+    }
+
+    /// A stable, machine-readable identifier for this kind of error
+    ///
+    /// Unknown users are reported as `invalid_token`, so clients cannot
+    /// probe for accounts.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AuthError::InvalidToken(_) | AuthError::UserNotFound => "invalid_token",
+            AuthError::Expired => "token_expired",
+            AuthError::Revoked => "token_revoked",
+            AuthError::AccountLocked => "account_locked",
+            AuthError::AccountDisabled => "account_disabled",
+            AuthError::RateLimited { .. } => "rate_limited",
+            AuthError::ProviderUnavailable { .. } => "provider_unavailable",
+            AuthError::DatabaseError(_) => "provider_error",
+            AuthError::Other(_) => "authentication_failed",
+        }
+    }
+
+    /// The variant's name in snake case, e.g. `user_not_found`
+    ///
+    /// Unlike [`code`](Self::code), this tells every variant apart, so it
+    /// is meant for logs and metrics rather than for clients.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AuthError::InvalidToken(_) => "invalid_token",
+            AuthError::Expired => "expired",
+            AuthError::Revoked => "revoked",
+            AuthError::AccountLocked => "account_locked",
+            AuthError::AccountDisabled => "account_disabled",
+            AuthError::RateLimited { .. } => "rate_limited",
+            AuthError::ProviderUnavailable { .. } => "provider_unavailable",
+            AuthError::DatabaseError(_) => "database_error",
+            AuthError::UserNotFound => "user_not_found",
+            AuthError::Other(_) => "other",
+        }
+    }
+}
+
 impl std::fmt::Display for AuthError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             AuthError::InvalidToken(msg) => write!(f, "Invalid token: {}", msg),
+            AuthError::Expired => write!(f, "Token has expired"),
+            AuthError::Revoked => write!(f, "Token has been revoked"),
+            AuthError::AccountLocked => write!(f, "Account is locked"),
+            AuthError::AccountDisabled => write!(f, "Account is disabled"),
+            AuthError::RateLimited { .. } => write!(f, "Too many authentication attempts"),
+            AuthError::ProviderUnavailable { message, .. } => write!(f, "Auth provider unavailable: {}", message),
             AuthError::DatabaseError(msg) => write!(f, "Database error: {}", msg),
             AuthError::UserNotFound => write!(f, "User not found"),
             AuthError::Other(msg) => write!(f, "{}", msg),
@@ -29,9 +145,19 @@ impl std::fmt::Display for AuthError {
     }
 }
 
-impl std::error::Error for AuthError {}
+impl std::error::Error for AuthError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AuthError::ProviderUnavailable { source: Some(source), .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
 
 /// A permission is a string identifier that represents a single capability
+///
+/// Permissions are made of `:`-separated segments, and grants may use `*`
+/// wildcards; see the [`permission`](crate::permission) module.
 pub type Permission = String;
 
 /// A role is a collection of permissions
@@ -41,11 +167,88 @@ pub struct Role {
     pub name: String,
     /// The permissions granted by this role
     pub permissions: HashSet<Permission>,
+    /// The roles this role inherits permissions from
+    pub parents: Vec<String>,
+}
+
+impl Role {
+    /// Create a new role with the given name and no permissions
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            permissions: HashSet::new(),
+            parents: Vec::new(),
+        }
+    }
+
+    /// Add a permission to the role
+    pub fn with_permission(mut self, permission: impl Into<String>) -> Self {
+        self.permissions.insert(permission.into());
+        self
+    }
+
+    /// Add multiple permissions to the role
+    pub fn with_permissions(mut self, permissions: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.permissions.extend(permissions.into_iter().map(Into::into));
+        self
+    }
+
+    /// Inherit every permission of the given parent role
+    pub fn with_parent(mut self, parent: impl Into<String>) -> Self {
+        self.parents.push(parent.into());
+        self
+    }
+
+    /// Inherit every permission of the given parent roles
+    pub fn with_parents(mut self, parents: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.parents.extend(parents.into_iter().map(Into::into));
+        self
+    }
+}
+
+/// Resolve role inheritance so that every role carries the permissions of
+/// all of its ancestors.
+///
+/// Parents that are not defined are ignored, and a cycle simply stops the
+/// walk at the first role that was already visited, so this never loops.
+pub fn resolve_role_hierarchy(roles: &HashMap<String, Role>) -> HashMap<String, Role> {
+    roles
+        .iter()
+        .map(|(name, role)| {
+            let mut permissions = HashSet::new();
+            let mut visited = HashSet::new();
+            let mut stack = vec![name.as_str()];
+
+            while let Some(current) = stack.pop() {
+                if !visited.insert(current) {
+                    continue;
+                }
+                if let Some(current_role) = roles.get(current) {
+                    permissions.extend(current_role.permissions.iter().cloned());
+                    stack.extend(current_role.parents.iter().map(String::as_str));
+                }
+            }
+
+            let resolved = Role {
+                name: role.name.clone(),
+                permissions,
+                parents: role.parents.clone(),
+            };
+            (name.clone(), resolved)
+        })
+        .collect()
 }
 
 /// User struct containing authentication and authorization information
-#[derive(Debug, Clone)]
-pub struct User {
+///
+/// As a request guard, `User` authenticates with the default provider. The
+/// type parameter selects a named provider instead, e.g. `User<SsoProvider>`
+/// with a [`NamedProvider`] implementation for `SsoProvider`.
+///
+/// `User` is `#[non_exhaustive]`: outside this crate, build it with
+/// [`User::new`] and the `with_*` methods rather than a struct literal.
+#[non_exhaustive]
+pub struct User<P = DefaultProvider> {
     /// The unique identifier for the user
     pub id: String,
     /// The username or display name
@@ -54,6 +257,14 @@ pub struct User {
     pub roles: Vec<String>,
     /// Direct permissions assigned to this user (in addition to roles)
     pub permissions: HashSet<Permission>,
+    /// Roles that only apply within a tenant, by tenant id
+    pub tenant_roles: HashMap<String, Vec<String>>,
+    /// OAuth2 scopes granted to the token the user authenticated with
+    pub scopes: HashSet<String>,
+    /// Role registry of the Rocket instance that authenticated this user,
+    /// if any. Falls back to the global registry when unset.
+    pub(crate) registry: Option<Arc<RoleRegistry>>,
+    provider: PhantomData<fn() -> P>,
 }
 
 impl User {
@@ -64,8 +275,36 @@ impl User {
             username: username.into(),
             roles: Vec::new(),
             permissions: HashSet::new(),
+            tenant_roles: HashMap::new(),
+            scopes: HashSet::new(),
+            registry: None,
+            provider: PhantomData,
         }
     }
+}
+
+impl<P> User<P> {
+    /// Convert into a user of another provider type
+    fn retag<Q>(self) -> User<Q> {
+        User {
+            id: self.id,
+            username: self.username,
+            roles: self.roles,
+            permissions: self.permissions,
+            tenant_roles: self.tenant_roles,
+            scopes: self.scopes,
+            registry: self.registry,
+            provider: PhantomData,
+        }
+    }
+
+    /// Forget which provider authenticated this user
+    ///
+    /// Useful to pass a `User<SsoProvider>` to APIs that take a plain
+    /// [`User`], such as [`Authorize`](crate::policy::Authorize).
+    pub fn into_user(self) -> User {
+        self.retag()
+    }
 
     /// Add a role to the user
     pub fn with_role(mut self, role: impl Into<String>) -> Self {
@@ -91,41 +330,120 @@ impl User {
         self
     }
 
+    /// Add an OAuth2 scope to the user
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.scopes.insert(scope.into());
+        self
+    }
+
+    /// Add multiple OAuth2 scopes to the user
+    pub fn with_scopes(mut self, scopes: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.scopes.extend(scopes.into_iter().map(Into::into));
+        self
+    }
+
+    /// Add a role that only applies within the given tenant
+    pub fn with_tenant_role(mut self, tenant: impl Into<String>, role: impl Into<String>) -> Self {
+        self.tenant_roles.entry(tenant.into()).or_default().push(role.into());
+        self
+    }
+
+    /// Add multiple roles that only apply within the given tenant
+    pub fn with_tenant_roles(
+        mut self,
+        tenant: impl Into<String>,
+        roles: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.tenant_roles
+            .entry(tenant.into())
+            .or_default()
+            .extend(roles.into_iter().map(Into::into));
+        self
+    }
+
+    /// Resolve role permissions against the given registry instead of the
+    /// global one
+    /// 
+    /// The `User` request guard does this automatically when the
+    /// [`RocketRoles`](crate::fairing::RocketRoles) fairing is attached.
+    pub fn with_registry(mut self, registry: Arc<RoleRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// The registry used to resolve this user's role permissions
+    fn registry(&self) -> &RoleRegistry {
+        match &self.registry {
+            Some(registry) => registry,
+            None => role_registry(),
+        }
+    }
+
     /// Check if the user has a specific role
     pub fn has_role(&self, role: &str) -> bool {
         self.roles.iter().any(|r| r == role)
     }
 
+    /// Check if the user's token was granted a specific OAuth2 scope
+    ///
+    /// Scopes are opaque strings and are compared exactly.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.contains(scope)
+    }
+
+    /// The roles that apply within a tenant: the user's global roles and
+    /// the roles bound to that tenant
+    pub fn roles_in(&self, tenant: &str) -> Vec<&str> {
+        self.roles
+            .iter()
+            .chain(self.tenant_roles.get(tenant).into_iter().flatten())
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Check if the user has a specific role within a tenant
+    pub fn has_role_in(&self, tenant: &str, role: &str) -> bool {
+        self.roles_in(tenant).contains(&role)
+    }
+
     /// Check if the user has a specific permission
+    ///
+    /// Grants may use wildcards, so `posts:*` covers `posts:edit`; see the
+    /// [`permission`](crate::permission) module.
     pub fn has_permission(&self, permission: &str) -> bool {
+        self.grants(self.roles.iter().map(String::as_str), permission)
+    }
+
+    /// Check if the user has a specific permission within a tenant, either
+    /// directly or through [`roles_in`](User::roles_in) that tenant
+    pub fn has_permission_in(&self, tenant: &str, permission: &str) -> bool {
+        self.grants(self.roles_in(tenant).into_iter(), permission)
+    }
+
+    /// Whether the user's direct permissions or the given roles grant the
+    /// permission
+    fn grants<'a>(&self, mut roles: impl Iterator<Item = &'a str>, permission: &str) -> bool {
         // First check direct permissions
-        if self.permissions.contains(permission) {
+        if self.permissions.contains(permission)
+            || self.permissions.iter().any(|grant| grant_matches(grant, permission))
+        {
             return true;
         }
 
-        // Then check permissions granted by roles
-        let roles = ROLES.get().expect("Roles not initialized");
-        for role_name in &self.roles {
-            if let Some(role) = roles.get(role_name) {
-                if role.permissions.contains(permission) {
-                    return true;
-                }
-            }
-        }
-
-        false
+        // Then check permissions granted by roles, including inherited ones
+        let sets = self.registry().permission_sets();
+        roles.any(|role_name| sets.get(role_name).is_some_and(|set| set.matches(permission)))
     }
     
     /// Get all permissions this user has (direct + from roles)
     pub fn all_permissions(&self) -> HashSet<String> {
         let mut all_perms = self.permissions.clone();
         
-        // Add permissions from roles
-        if let Some(roles) = ROLES.get() {
-            for role_name in &self.roles {
-                if let Some(role) = roles.get(role_name) {
-                    all_perms.extend(role.permissions.clone());
-                }
+        // Add permissions from roles, including inherited ones
+        let roles = self.registry().roles();
+        for role_name in &self.roles {
+            if let Some(role) = roles.get(role_name) {
+                all_perms.extend(role.permissions.clone());
             }
         }
         
@@ -133,6 +451,80 @@ impl User {
     }
 }
 
+impl<P> Clone for User<P> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id.clone(),
+            username: self.username.clone(),
+            roles: self.roles.clone(),
+            permissions: self.permissions.clone(),
+            tenant_roles: self.tenant_roles.clone(),
+            scopes: self.scopes.clone(),
+            registry: self.registry.clone(),
+            provider: PhantomData,
+        }
+    }
+}
+
+impl<P> std::fmt::Debug for User<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("User")
+            .field("id", &self.id)
+            .field("username", &self.username)
+            .field("roles", &self.roles)
+            .field("permissions", &self.permissions)
+            .field("tenant_roles", &self.tenant_roles)
+            .field("scopes", &self.scopes)
+            .field("registry", &self.registry)
+            .finish()
+    }
+}
+
+/// The `NamedProvider` trait links a type to the name of an auth provider,
+/// so `User<T>` authenticates with that provider
+///
+/// The type can be the provider itself or a marker:
+///
+/// ```rust,ignore
+/// pub struct Sso;
+///
+/// impl NamedProvider for Sso {
+///     const NAME: &'static str = "sso";
+/// }
+///
+/// #[get("/admin")]
+/// fn admin(user: User<Sso>) -> String { ... }
+/// ```
+pub trait NamedProvider: 'static {
+    /// The name the provider is registered under
+    const NAME: &'static str;
+}
+
+/// Selects the provider of the unnamed [`User`] guard: the one passed to
+/// [`RocketRoles::new`] or [`register_auth_provider`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DefaultProvider;
+
+/// Types that select the provider a [`User`] guard authenticates with
+///
+/// Implemented by [`DefaultProvider`] and every [`NamedProvider`].
+pub trait ProviderSelector: 'static {
+    /// The provider's name, or `None` for the default provider
+    fn provider_name() -> Option<&'static str>;
+}
+
+impl ProviderSelector for DefaultProvider {
+    fn provider_name() -> Option<&'static str> {
+        None
+    }
+}
+
+impl<P: NamedProvider> ProviderSelector for P {
+    fn provider_name() -> Option<&'static str> {
+        Some(P::NAME)
+    }
+}
+
 /// The `AuthProvider` trait must be implemented by any authentication provider
 /// to be used with rocket-easy-auth.
 #[async_trait]
@@ -149,79 +541,347 @@ pub trait AuthProvider: Send + Sync + 'static {
     async fn authenticate_token(&self, token: &str) -> Result<User, AuthError>;
 }
 
+/// Shared providers can be registered while keeping a handle to them, e.g.
+/// to invalidate a [`CachedAuthProvider`](crate::cache::CachedAuthProvider)
+#[async_trait]
+impl<T: AuthProvider + ?Sized> AuthProvider for Arc<T> {
+    async fn authenticate_token(&self, token: &str) -> Result<User, AuthError> {
+        (**self).authenticate_token(token).await
+    }
+}
+
 /// Rocket request guard for authenticated users
+/// 
+/// On failure the [`AuthRejection`] is also stashed in the request-local
+/// cache, where the catchers in [`rejection`](crate::rejection) pick it up.
 #[rocket::async_trait]
-impl<'r> rocket::request::FromRequest<'r> for User {
-    type Error = String;
+impl<'r, P: ProviderSelector> rocket::request::FromRequest<'r> for User<P> {
+    type Error = AuthRejection;
+
+    async fn from_request(request: &'r rocket::request::Request<'_>) -> rocket::request::Outcome<Self, Self::Error> {
+        authenticate_request(request, P::provider_name()).await.map(User::retag)
+    }
+}
+
+/// Authenticate the request with the default provider, or with the one
+/// registered under the given name, the way the [`User`] guard does
+///
+/// Useful in custom guards that pick the provider at runtime. On failure
+/// the rejection is stashed for the catchers.
+pub async fn authenticate_request(
+    request: &rocket::request::Request<'_>,
+    provider: Option<&'static str>,
+) -> rocket::request::Outcome<User, AuthRejection> {
+    use rocket::request::Outcome;
+
+    match authenticate(request, provider).await {
+        Ok(user) => Outcome::Success(user),
+        Err(rejection) => {
+            rejection.stash(request);
+            Outcome::Error((rejection.status(), rejection))
+        }
+    }
+}
+
+/// Rocket request guard for routes that also serve anonymous users
+///
+/// Holds `None` when the request carries no credential. A credential that is
+/// present but invalid is still rejected like the [`User`] guard would,
+/// unless the fairing was built with
+/// [`RocketRoles::with_lenient_optional_auth`], in which case such requests
+/// are treated as anonymous too. Backend failures are always rejected.
+///
+/// Prefer this over `Option<User>`, which Rocket turns into `None` on every
+/// failure, including outages. Like [`User`], the type parameter selects a
+/// named provider, e.g. `MaybeUser<SsoProvider>`.
+///
+/// ```rust,ignore
+/// #[get("/")]
+/// fn home(user: MaybeUser) -> String {
+///     match user.as_ref() {
+///         Some(user) => format!("Welcome back, {}", user.username),
+///         None => "Welcome, stranger".to_string(),
+///     }
+/// }
+/// ```
+pub struct MaybeUser<P = DefaultProvider>(pub Option<User<P>>);
+
+impl<P> MaybeUser<P> {
+    /// The authenticated user, if any
+    pub fn into_inner(self) -> Option<User<P>> {
+        self.0
+    }
+}
+
+impl<P> Clone for MaybeUser<P> {
+    fn clone(&self) -> Self {
+        MaybeUser(self.0.clone())
+    }
+}
+
+impl<P> std::fmt::Debug for MaybeUser<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("MaybeUser").field(&self.0).finish()
+    }
+}
+
+impl<P> std::ops::Deref for MaybeUser<P> {
+    type Target = Option<User<P>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<P> From<MaybeUser<P>> for Option<User<P>> {
+    fn from(user: MaybeUser<P>) -> Self {
+        user.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, P: ProviderSelector> rocket::request::FromRequest<'r> for MaybeUser<P> {
+    type Error = AuthRejection;
 
     async fn from_request(request: &'r rocket::request::Request<'_>) -> rocket::request::Outcome<Self, Self::Error> {
-        use rocket::http::Status;
         use rocket::request::Outcome;
 
-        // Get the auth header
-        let auth_header = match request.headers().get_one("Authorization") {
-            Some(header) => header,
-            None => {
-                return Outcome::Error((
-                    Status::Unauthorized,
-                    "Authorization header is required".to_string(),
-                ));
+        let lenient = request
+            .rocket()
+            .state::<RocketRoles>()
+            .is_some_and(|state| state.lenient_optional_auth);
+
+        match authenticate(request, P::provider_name()).await {
+            Ok(user) => Outcome::Success(MaybeUser(Some(user.retag()))),
+            Err(AuthRejection::MissingCredentials) => Outcome::Success(MaybeUser(None)),
+            Err(rejection) if lenient && rejection.status() == rocket::http::Status::Unauthorized => {
+                Outcome::Success(MaybeUser(None))
             }
-        };
+            Err(rejection) => {
+                rejection.stash(request);
+                Outcome::Error((rejection.status(), rejection))
+            }
+        }
+    }
+}
 
-        // Parse the token (assuming Bearer token)
-        let token = if auth_header.starts_with("Bearer ") {
-            &auth_header[7..]
-        } else {
-            return Outcome::Error((
-                Status::Unauthorized,
-                "Invalid authorization format".to_string(),
-            ));
-        };
+// Outcomes of authenticating a request, by provider name, cached for the
+// lifetime of the request
+#[derive(Default)]
+struct AuthOutcomes(Mutex<HashMap<Option<&'static str>, Result<User, AuthRejection>>>);
 
-        // Get the configured auth provider and validate token
-        let provider = match AUTH_PROVIDER.get() {
-            Some(provider) => provider,
-            None => {
-                return Outcome::Error((
-                    Status::InternalServerError,
-                    "Auth provider not registered".to_string(),
-                ));
-            }
+/// Authenticate the request once per provider and reuse the outcome,
+/// success or failure, for every guard that asks for it
+async fn authenticate(
+    request: &rocket::request::Request<'_>,
+    provider: Option<&'static str>,
+) -> Result<User, AuthRejection> {
+    let outcomes = request.local_cache(AuthOutcomes::default);
+    if let Some(outcome) = outcomes.0.lock().unwrap_or_else(PoisonError::into_inner).get(&provider) {
+        return outcome.clone();
+    }
+
+    let outcome = authenticate_uncached(request, provider).await;
+    audit::record(request, || {
+        let (user, rejection) = match &outcome {
+            Ok(user) => (Some(user), None),
+            Err(rejection) => (None, Some(rejection)),
         };
+        AuditEvent::new(request, AuditKind::Authentication, user, rejection)
+    });
+    outcomes
+        .0
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(provider, outcome.clone());
+    outcome
+}
+
+/// Extract the token from the request and authenticate it
+async fn authenticate_uncached(
+    request: &rocket::request::Request<'_>,
+    provider: Option<&'static str>,
+) -> Result<User, AuthRejection> {
+    // Prefer the configuration managed by the RocketRoles fairing and
+    // fall back to the globally registered one
+    let state = request.rocket().state::<RocketRoles>();
 
-        match provider.authenticate_token(token).await {
-            Ok(user) => Outcome::Success(user),
-            Err(e) => Outcome::Error((
-                Status::Unauthorized,
-                format!("Authentication failed: {}", e),
-            )),
+    // Extract the token using the configured extractor chain
+    let extractors = match state {
+        Some(state) => state.extractors.as_ref(),
+        None => get_token_extractors(),
+    };
+    let token = match telemetry::extract_token(extractors, request) {
+        Ok(Some(token)) => token,
+        Ok(None) => return Err(AuthRejection::MissingCredentials),
+        Err(e) => return Err(AuthRejection::MalformedCredentials(e.to_string())),
+    };
+
+    // Get the configured auth provider and validate token
+    let name = provider;
+    let provider = match name {
+        None => state
+            .map(|state| state.provider.clone())
+            .or_else(|| GLOBAL_AUTH.get().map(|global| global.provider.clone())),
+        Some(name) => state
+            .and_then(|state| state.named_providers.get(name).cloned())
+            .or_else(|| named_providers().get(name).cloned())
+            .or_else(|| {
+                rocket::error!("No auth provider registered under the name '{}'", name);
+                None
+            }),
+    };
+    let Some(provider) = provider else {
+        return Err(AuthRejection::ProviderUnregistered);
+    };
+
+    match telemetry::authenticate_token(provider.as_ref(), name, &token).await {
+        Ok(user) => match state {
+            Some(state) => Ok(user.with_registry(state.registry.clone())),
+            None => Ok(user),
+        },
+        Err(e) => {
+            if !e.is_client_error() {
+                rocket::error!("Authentication failed: {}", e);
+            }
+            Err(AuthRejection::InvalidToken(e))
         }
     }
 }
 
-// Global instance of the auth provider
-static AUTH_PROVIDER: OnceCell<Arc<dyn AuthProvider>> = OnceCell::new();
+// Global auth provider and the extractors that find its tokens, used when
+// the RocketRoles fairing is not attached. Kept in one cell so that they are
+// registered together or not at all
+static GLOBAL_AUTH: OnceCell<GlobalAuth> = OnceCell::new();
 
-// Global mapping of role names to their permissions
-static ROLES: OnceCell<HashMap<String, Role>> = OnceCell::new();
+struct GlobalAuth {
+    provider: Arc<dyn AuthProvider>,
+    extractors: ExtractorChain,
+}
+
+// Global auth providers registered under a name
+static NAMED_PROVIDERS: OnceCell<RwLock<HashMap<String, Arc<dyn AuthProvider>>>> = OnceCell::new();
+
+// Bearer header chain, used until a provider is registered
+static DEFAULT_EXTRACTORS: OnceCell<ExtractorChain> = OnceCell::new();
+
+// Global registry of roles and their permissions
+static ROLES: OnceCell<Arc<RoleRegistry>> = OnceCell::new();
+
+/// Error returned when registering a global auth provider after one has
+/// already been registered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlreadyRegistered;
+
+impl std::fmt::Display for AlreadyRegistered {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "an auth provider is already registered")
+    }
+}
+
+impl std::error::Error for AlreadyRegistered {}
 
 /// Register an authentication provider for the application
 /// 
+/// Tokens are read from `Authorization: Bearer <token>`. Register the
+/// catchers from [`rejection`](crate::rejection) as well: they add the
+/// `WWW-Authenticate` challenge to rejected requests.
+/// 
+/// # Arguments
+/// 
+/// * `provider` - The authentication provider to use
+/// 
+/// # Errors
+/// 
+/// Returns [`AlreadyRegistered`] if a provider has already been
+/// registered, which is kept. Attach the [`RocketRoles`] fairing instead
+/// to configure several Rocket instances in the same process.
+pub fn register_auth_provider(provider: impl AuthProvider) -> Result<(), AlreadyRegistered> {
+    register_auth_provider_with_extractors(provider, ExtractorChain::default())
+}
+
+/// Register an authentication provider together with the chain of
+/// extractors used to find the token in each request
+/// 
 /// # Arguments
 /// 
 /// * `provider` - The authentication provider to use
-pub fn register_auth_provider(provider: impl AuthProvider) {
-    let _ = AUTH_PROVIDER.set(Arc::new(provider));
+/// * `extractors` - The extractors to try, in order
+/// 
+/// # Errors
+/// 
+/// Returns [`AlreadyRegistered`] if a provider has already been
+/// registered, in which case neither the provider nor the extractors
+/// are changed.
+pub fn register_auth_provider_with_extractors(
+    provider: impl AuthProvider,
+    extractors: ExtractorChain,
+) -> Result<(), AlreadyRegistered> {
+    GLOBAL_AUTH
+        .set(GlobalAuth {
+            provider: Arc::new(provider),
+            extractors,
+        })
+        .map_err(|_| AlreadyRegistered)
+}
+
+/// Register an authentication provider under a name, for routes that select
+/// it with `User<P>` or the `provider` option of the attribute macros
+///
+/// Registering another provider under the same name replaces it. A
+/// provider added to the [`RocketRoles`] fairing with
+/// [`with_named_provider`](RocketRoles::with_named_provider) takes
+/// precedence over one registered here under the same name.
+///
+/// # Arguments
+///
+/// * `name` - The name routes select the provider by
+/// * `provider` - The authentication provider to use
+pub fn register_named_auth_provider(name: impl Into<String>, provider: impl AuthProvider) {
+    let providers = NAMED_PROVIDERS.get_or_init(Default::default);
+    providers
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(name.into(), Arc::new(provider));
+}
+
+/// Remove the provider registered under a name with
+/// [`register_named_auth_provider`], returning it if there was one
+///
+/// Routes that select the name fail with 500 afterwards, unless the
+/// fairing provides it.
+pub fn unregister_named_auth_provider(name: &str) -> Option<Arc<dyn AuthProvider>> {
+    let providers = NAMED_PROVIDERS.get_or_init(Default::default);
+    providers.write().unwrap_or_else(PoisonError::into_inner).remove(name)
+}
+
+/// The globally registered named providers
+fn named_providers() -> RwLockReadGuard<'static, HashMap<String, Arc<dyn AuthProvider>>> {
+    NAMED_PROVIDERS
+        .get_or_init(Default::default)
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
 }
 
 /// Register roles and their permissions
 /// 
+/// Role inheritance is resolved here, once, so permission checks never
+/// have to walk the hierarchy. Calling this again atomically replaces the
+/// previously registered roles.
+/// 
 /// # Arguments
 /// 
 /// * `roles` - A map of role names to their permissions
 pub fn register_roles(roles: HashMap<String, Role>) {
-    let _ = ROLES.set(roles);
+    role_registry().replace_all(roles);
+}
+
+/// Get the global role registry, used when the
+/// [`RocketRoles`](crate::fairing::RocketRoles) fairing is not attached
+/// 
+/// Roles can be changed at runtime through the returned registry.
+pub fn role_registry() -> &'static Arc<RoleRegistry> {
+    ROLES.get_or_init(|| Arc::new(RoleRegistry::new()))
 }
 
 /// Get the current auth provider
@@ -229,15 +889,15 @@ pub fn register_roles(roles: HashMap<String, Role>) {
 /// # Panics
 /// 
 /// Panics if no auth provider has been registered
+#[cfg_attr(not(test), allow(dead_code))]
 pub(crate) fn get_auth_provider() -> &'static Arc<dyn AuthProvider> {
-    AUTH_PROVIDER.get().expect("Auth provider not registered")
+    &GLOBAL_AUTH.get().expect("Auth provider not registered").provider
 }
 
-/// Get the registered roles
-/// 
-/// # Panics
-/// 
-/// Panics if roles have not been registered
-pub(crate) fn get_roles() -> &'static HashMap<String, Role> {
-    ROLES.get().expect("Roles not registered")
+/// Get the registered token extractors, or the default Bearer chain
+pub(crate) fn get_token_extractors() -> &'static ExtractorChain {
+    match GLOBAL_AUTH.get() {
+        Some(global) => &global.extractors,
+        None => DEFAULT_EXTRACTORS.get_or_init(ExtractorChain::default),
+    }
 }
\ No newline at end of file
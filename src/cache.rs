@@ -0,0 +1,266 @@
+//! Caching decorator for auth providers
+//!
+//! [`CachedAuthProvider`] wraps any [`AuthProvider`] and remembers the user
+//! returned for each token, so repeated requests with the same token do not
+//! hit the backing store every time.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use rocket_roles::cache::CachedAuthProvider;
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! let provider = Arc::new(
+//!     CachedAuthProvider::new(PostgresAuthProvider::new(&database_url).await?)
+//!         .with_ttl(Duration::from_secs(300))
+//!         .with_negative_ttl(Duration::from_secs(10)),
+//! );
+//! register_auth_provider(provider.clone())?;
+//!
+//! // Later, on logout or when a user's roles change
+//! provider.invalidate_user("42");
+//! ```
+
+use crate::auth::{AuthError, AuthProvider, User};
+use async_trait::async_trait;
+use rocket::tokio::time::Instant;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// Default time a successful authentication is cached
+const DEFAULT_TTL: Duration = Duration::from_secs(60);
+
+/// Default maximum number of cached tokens
+const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+/// A cached authentication result
+struct Entry {
+    result: Result<User, AuthError>,
+    /// When the entry expires, and a sequence number that tells apart
+    /// entries expiring at the same instant
+    expiry: (Instant, u64),
+}
+
+/// Cached results by token, also ordered by expiry for eviction
+#[derive(Default)]
+struct Entries {
+    by_token: HashMap<String, Entry>,
+    by_expiry: BTreeMap<(Instant, u64), String>,
+    next_sequence: u64,
+    /// Bumped on every invalidation, so that results fetched from the inner
+    /// provider before it are not cached
+    generation: u64,
+}
+
+impl Entries {
+    /// The cached result for a token, unless it expired
+    fn get(&mut self, token: &str, now: Instant) -> Option<Result<User, AuthError>> {
+        let entry = self.by_token.get(token)?;
+        if entry.expiry.0 > now {
+            return Some(entry.result.clone());
+        }
+        self.remove(token);
+        None
+    }
+
+    fn insert(&mut self, token: &str, result: Result<User, AuthError>, expires_at: Instant) {
+        self.remove(token);
+        let expiry = (expires_at, self.next_sequence);
+        self.next_sequence += 1;
+        self.by_expiry.insert(expiry, token.to_string());
+        self.by_token.insert(token.to_string(), Entry { result, expiry });
+    }
+
+    fn remove(&mut self, token: &str) {
+        if let Some(entry) = self.by_token.remove(token) {
+            self.by_expiry.remove(&entry.expiry);
+        }
+    }
+
+    /// Remove the entry closest to expiring, which may have expired
+    /// already, or return false when there is none
+    fn pop_soonest(&mut self) -> bool {
+        match self.by_expiry.pop_first() {
+            Some((_, token)) => self.by_token.remove(&token).is_some(),
+            None => false,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.by_token.clear();
+        self.by_expiry.clear();
+    }
+}
+
+/// Auth provider that caches the results of another provider by token
+///
+/// Successful results are cached for the TTL. Failures are only cached when
+/// a negative TTL is configured, and only when the failure is definitive,
+/// such as [`AuthError::InvalidToken`] or [`AuthError::Revoked`]. Rate
+/// limiting and backend failures are never cached.
+///
+/// The cache does not know when tokens expire, so a successful result is
+/// kept for the full TTL even if the token expires sooner: the user stays
+/// authenticated until the entry expires or is invalidated. Keep the TTL
+/// well below the lifetime of your tokens, and invalidate tokens that are
+/// revoked.
+///
+/// A result the inner provider returns after an invalidation that happened
+/// while it was being fetched is not cached, so it cannot bring back what
+/// was just forgotten.
+pub struct CachedAuthProvider<P: AuthProvider> {
+    inner: P,
+    entries: Mutex<Entries>,
+    ttl: Duration,
+    negative_ttl: Option<Duration>,
+    max_entries: usize,
+}
+
+impl<P: AuthProvider> CachedAuthProvider<P> {
+    /// Wrap a provider, caching successful results for 60 seconds
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            entries: Mutex::new(Entries::default()),
+            ttl: DEFAULT_TTL,
+            negative_ttl: None,
+            max_entries: DEFAULT_MAX_ENTRIES,
+        }
+    }
+
+    /// Cache successful results for the given time
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Also cache definitive failures, for the given (usually shorter) time
+    pub fn with_negative_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_ttl = Some(ttl);
+        self
+    }
+
+    /// Cache at most this many tokens (default 10,000)
+    ///
+    /// When full, expired entries are dropped first, then the entries
+    /// closest to expiring.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// The wrapped provider
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// Forget the cached result for a token, e.g. on logout
+    pub fn invalidate_token(&self, token: &str) {
+        let mut entries = self.entries();
+        entries.remove(token);
+        entries.generation += 1;
+    }
+
+    /// Forget every cached result for a user, e.g. when their roles change
+    pub fn invalidate_user(&self, id: &str) {
+        let mut entries = self.entries();
+        let tokens: Vec<String> = entries
+            .by_token
+            .iter()
+            .filter(|(_, entry)| matches!(&entry.result, Ok(user) if user.id == id))
+            .map(|(token, _)| token.clone())
+            .collect();
+        for token in tokens {
+            entries.remove(&token);
+        }
+        entries.generation += 1;
+    }
+
+    /// Forget every cached result
+    pub fn clear(&self) {
+        let mut entries = self.entries();
+        entries.clear();
+        entries.generation += 1;
+    }
+
+    /// Number of cached tokens, including ones that have expired but were
+    /// not evicted yet
+    pub fn len(&self) -> usize {
+        self.entries().by_token.len()
+    }
+
+    /// Whether nothing is cached
+    pub fn is_empty(&self) -> bool {
+        self.entries().by_token.is_empty()
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// How long a result should be cached, if at all
+    fn ttl_for(&self, result: &Result<User, AuthError>) -> Option<Duration> {
+        match result {
+            Ok(_) => Some(self.ttl),
+            Err(
+                AuthError::InvalidToken(_)
+                | AuthError::Expired
+                | AuthError::Revoked
+                | AuthError::AccountLocked
+                | AuthError::AccountDisabled
+                | AuthError::UserNotFound,
+            ) => self.negative_ttl,
+            Err(_) => None,
+        }
+    }
+
+    /// Cache a result, unless the cache was invalidated since `generation`
+    fn insert(&self, token: &str, result: Result<User, AuthError>, ttl: Duration, generation: u64) {
+        let mut entries = self.entries();
+        if self.max_entries == 0 || entries.generation != generation {
+            return;
+        }
+
+        // Expired entries come first in expiry order, so this drops them
+        // before any live entry
+        while entries.by_token.len() >= self.max_entries && !entries.by_token.contains_key(token) {
+            if !entries.pop_soonest() {
+                break;
+            }
+        }
+
+        entries.insert(token, result, Instant::now() + ttl);
+    }
+}
+
+#[async_trait]
+impl<P: AuthProvider> AuthProvider for CachedAuthProvider<P> {
+    async fn authenticate_token(&self, token: &str) -> Result<User, AuthError> {
+        let generation = {
+            let mut entries = self.entries();
+            if let Some(result) = entries.get(token, Instant::now()) {
+                return result;
+            }
+            entries.generation
+        };
+
+        let result = self.inner.authenticate_token(token).await;
+        if let Some(ttl) = self.ttl_for(&result) {
+            self.insert(token, result.clone(), ttl, generation);
+        }
+        result
+    }
+}
+
+impl<P: AuthProvider> std::fmt::Debug for CachedAuthProvider<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachedAuthProvider")
+            .field("entries", &self.len())
+            .field("ttl", &self.ttl)
+            .field("negative_ttl", &self.negative_ttl)
+            .field("max_entries", &self.max_entries)
+            .finish_non_exhaustive()
+    }
+}
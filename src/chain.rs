@@ -0,0 +1,203 @@
+//! Composite provider that tries several auth providers in turn
+//!
+//! A [`ProviderChain`] is itself an [`AuthProvider`], so it can be
+//! registered wherever a single provider can. This lets an application
+//! accept several kinds of tokens, e.g. while migrating from opaque tokens
+//! to JWTs:
+//!
+//! ```rust,ignore
+//! use rocket_roles::chain::{ProviderChain, TokenMatch};
+//!
+//! let provider = ProviderChain::new()
+//!     // Only JWTs are sent to the JWT provider
+//!     .with_provider_for(TokenMatch::jwt(), JwtAuthProvider::hs256(secret))
+//!     // API keys are recognized by their prefix
+//!     .with_provider_for(TokenMatch::prefix("sk_"), ApiKeyProvider::new(pool.clone()))
+//!     // Everything else is looked up as a legacy opaque token
+//!     .with_provider(LegacyTokenProvider::new(pool));
+//!
+//! register_auth_provider(provider)?;
+//! ```
+//!
+//! Providers whose [`TokenMatch`] accepts the token are tried in the order
+//! they were added. By default, a provider that does not recognize the
+//! token, or is unavailable, passes it on to the next one; see
+//! [`FallThrough`] for the alternatives.
+
+use crate::auth::{AuthError, AuthProvider, User};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Which tokens a provider in a chain is asked about
+#[derive(Clone)]
+pub enum TokenMatch {
+    /// Every token
+    Any,
+    /// Tokens starting with the given prefix, e.g. `sk_`
+    Prefix(String),
+    /// Tokens shaped like a JWT: three base64url segments separated by dots
+    Jwt,
+    /// Tokens accepted by the given function
+    Custom(Arc<dyn Fn(&str) -> bool + Send + Sync>),
+}
+
+impl TokenMatch {
+    /// Match tokens starting with the given prefix
+    pub fn prefix(prefix: impl Into<String>) -> Self {
+        TokenMatch::Prefix(prefix.into())
+    }
+
+    /// Match tokens shaped like a JWT
+    pub fn jwt() -> Self {
+        TokenMatch::Jwt
+    }
+
+    /// Match tokens accepted by the given function
+    pub fn custom(matches: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        TokenMatch::Custom(Arc::new(matches))
+    }
+
+    /// Whether the token should be passed to the provider
+    pub fn matches(&self, token: &str) -> bool {
+        match self {
+            TokenMatch::Any => true,
+            TokenMatch::Prefix(prefix) => token.starts_with(prefix.as_str()),
+            TokenMatch::Jwt => {
+                let is_base64url = |segment: &str| {
+                    !segment.is_empty()
+                        && segment.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+                };
+                let segments: Vec<&str> = token.split('.').collect();
+                segments.len() == 3 && is_base64url(segments[0]) && is_base64url(segments[1])
+            }
+            TokenMatch::Custom(matches) => matches(token),
+        }
+    }
+}
+
+impl std::fmt::Debug for TokenMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenMatch::Any => f.write_str("Any"),
+            TokenMatch::Prefix(prefix) => f.debug_tuple("Prefix").field(prefix).finish(),
+            TokenMatch::Jwt => f.write_str("Jwt"),
+            TokenMatch::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// When a failed provider passes the token on to the next one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FallThrough {
+    /// When the provider does not recognize the token
+    /// ([`AuthError::InvalidToken`] or [`AuthError::UserNotFound`]) or is
+    /// unavailable. A token it recognized but refused, e.g. because it
+    /// expired or was revoked, is not passed on, and neither is one that
+    /// failed with [`AuthError::Other`].
+    #[default]
+    Unrecognized,
+    /// Never: the first provider that accepts the token by its
+    /// [`TokenMatch`] decides
+    Never,
+}
+
+impl FallThrough {
+    fn passes_on(&self, error: &AuthError) -> bool {
+        match self {
+            FallThrough::Unrecognized => {
+                matches!(error, AuthError::InvalidToken(_) | AuthError::UserNotFound) || !error.is_client_error()
+            }
+            FallThrough::Never => false,
+        }
+    }
+}
+
+/// A provider in the chain and the tokens it is asked about
+struct Link {
+    matcher: TokenMatch,
+    provider: Arc<dyn AuthProvider>,
+}
+
+/// Auth provider that tries several providers in order
+///
+/// When no provider accepts the token, the chain fails with the error of a
+/// provider that was unavailable, if any, so clients are told to retry
+/// rather than that their token is invalid. Otherwise it fails with the
+/// last provider's error.
+#[derive(Default)]
+pub struct ProviderChain {
+    links: Vec<Link>,
+    fall_through: FallThrough,
+}
+
+impl ProviderChain {
+    /// Create an empty chain, which rejects every token
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a provider that is asked about every token
+    pub fn with_provider(self, provider: impl AuthProvider) -> Self {
+        self.with_provider_for(TokenMatch::Any, provider)
+    }
+
+    /// Add a provider that is only asked about tokens the matcher accepts
+    pub fn with_provider_for(mut self, matcher: TokenMatch, provider: impl AuthProvider) -> Self {
+        self.links.push(Link {
+            matcher,
+            provider: Arc::new(provider),
+        });
+        self
+    }
+
+    /// Choose when a failed provider passes the token on to the next one
+    pub fn with_fall_through(mut self, fall_through: FallThrough) -> Self {
+        self.fall_through = fall_through;
+        self
+    }
+
+    /// Number of providers in the chain
+    pub fn len(&self) -> usize {
+        self.links.len()
+    }
+
+    /// Whether the chain has no providers
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+}
+
+#[async_trait]
+impl AuthProvider for ProviderChain {
+    async fn authenticate_token(&self, token: &str) -> Result<User, AuthError> {
+        let mut unavailable = None;
+        let mut last_error = None;
+
+        for link in self.links.iter().filter(|link| link.matcher.matches(token)) {
+            match link.provider.authenticate_token(token).await {
+                Ok(user) => return Ok(user),
+                Err(e) if self.fall_through.passes_on(&e) => {
+                    if !e.is_client_error() {
+                        unavailable.get_or_insert(e);
+                    } else {
+                        last_error = Some(e);
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(unavailable
+            .or(last_error)
+            .unwrap_or_else(|| AuthError::InvalidToken("No provider accepts this token".to_string())))
+    }
+}
+
+impl std::fmt::Debug for ProviderChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProviderChain")
+            .field("matchers", &self.links.iter().map(|link| &link.matcher).collect::<Vec<_>>())
+            .field("fall_through", &self.fall_through)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,207 @@
+//! Loading role definitions from configuration files
+//!
+//! Roles can be kept in a TOML, JSON or YAML document instead of Rust
+//! source. The document is a map of role names to their definition:
+//!
+//! ```toml
+//! [user]
+//! permissions = ["view_profile", "edit_profile"]
+//!
+//! [moderator]
+//! parents = ["user"]
+//! permissions = ["delete_post", "edit_post"]
+//! ```
+//!
+//! Each format is behind a cargo feature of the same name (`toml`, `json`
+//! and `yaml`). The same layout under a `roles` key in `Rocket.toml`, such
+//! as `[default.roles.user]`, is picked up by
+//! [`RocketRoles::from_config`](crate::fairing::RocketRoles::from_config)
+//! without any extra feature.
+
+use crate::auth::Role;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+/// The definition of a single role in a configuration file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RoleSpec {
+    /// The permissions granted by this role
+    pub permissions: Vec<String>,
+    /// The roles this role inherits permissions from
+    pub parents: Vec<String>,
+}
+
+/// Error returned when a role configuration cannot be loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoleConfigError {
+    /// The 1-based line of the offending entry, when it is known
+    pub line: Option<usize>,
+    /// Description of the problem
+    pub message: String,
+}
+
+impl std::fmt::Display for RoleConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for RoleConfigError {}
+
+/// Parse roles from a TOML document
+#[cfg(feature = "toml")]
+pub fn roles_from_toml_str(source: &str) -> Result<HashMap<String, Role>, RoleConfigError> {
+    let specs: HashMap<String, RoleSpec> = toml::from_str(source).map_err(|e| RoleConfigError {
+        line: e.span().map(|span| line_at(source, span.start)),
+        message: e.message().to_string(),
+    })?;
+    build_roles(specs, Some(source))
+}
+
+/// Parse roles from a JSON document
+#[cfg(feature = "json")]
+pub fn roles_from_json_str(source: &str) -> Result<HashMap<String, Role>, RoleConfigError> {
+    let specs: HashMap<String, RoleSpec> = serde_json::from_str(source).map_err(|e| RoleConfigError {
+        line: Some(e.line()).filter(|line| *line > 0),
+        message: e.to_string(),
+    })?;
+    build_roles(specs, Some(source))
+}
+
+/// Parse roles from a YAML document
+#[cfg(feature = "yaml")]
+pub fn roles_from_yaml_str(source: &str) -> Result<HashMap<String, Role>, RoleConfigError> {
+    let specs: HashMap<String, RoleSpec> = serde_yaml::from_str(source).map_err(|e| RoleConfigError {
+        line: e.location().map(|location| location.line()),
+        message: e.to_string(),
+    })?;
+    build_roles(specs, Some(source))
+}
+
+/// Validate role specs and turn them into roles
+///
+/// Parents that are not defined and inheritance cycles are rejected. When
+/// the source document is given, errors point at the line of the offending
+/// entry.
+pub fn build_roles(
+    specs: HashMap<String, RoleSpec>,
+    source: Option<&str>,
+) -> Result<HashMap<String, Role>, RoleConfigError> {
+    // Sort so the reported error does not depend on hash map ordering
+    let mut names: Vec<&String> = specs.keys().collect();
+    names.sort();
+
+    for name in &names {
+        for parent in &specs[*name].parents {
+            if !specs.contains_key(parent) {
+                return Err(RoleConfigError {
+                    line: source.and_then(|source| locate(source, name, parent)),
+                    message: format!("role '{}' has unknown parent role '{}'", name, parent),
+                });
+            }
+        }
+    }
+
+    for name in &names {
+        let mut path = vec![name.as_str()];
+        if let Some(cycle) = find_cycle(&specs, &mut path, &mut HashSet::new()) {
+            let parent = cycle[1];
+            return Err(RoleConfigError {
+                line: source.and_then(|source| locate(source, name, parent)),
+                message: format!("role inheritance cycle: {}", cycle.join(" -> ")),
+            });
+        }
+    }
+
+    Ok(specs
+        .into_iter()
+        .map(|(name, spec)| {
+            let role = Role::new(name.clone())
+                .with_permissions(spec.permissions)
+                .with_parents(spec.parents);
+            (name, role)
+        })
+        .collect())
+}
+
+/// Depth-first search for a path that leads back to its starting role
+fn find_cycle<'a>(
+    specs: &'a HashMap<String, RoleSpec>,
+    path: &mut Vec<&'a str>,
+    visited: &mut HashSet<&'a str>,
+) -> Option<Vec<&'a str>> {
+    let current = *path.last()?;
+    for parent in specs.get(current).map(|spec| spec.parents.as_slice()).unwrap_or_default() {
+        if parent == path[0] {
+            let mut cycle = path.clone();
+            cycle.push(parent);
+            return Some(cycle);
+        }
+        // Roles already explored from this start cannot lead back to it
+        if !visited.insert(parent) {
+            continue;
+        }
+        path.push(parent);
+        if let Some(cycle) = find_cycle(specs, path, visited) {
+            return Some(cycle);
+        }
+        path.pop();
+    }
+    None
+}
+
+/// Best-effort line of `needle` within the definition of `role`
+///
+/// Finds the line that introduces the role, as a table header such as
+/// `[user]` or `[roles.user]` or as a key such as `user =`, `"user":` or
+/// `user:`, then the first mention of the needle as a whole name after it.
+fn locate(source: &str, role: &str, needle: &str) -> Option<usize> {
+    let mut line_start = 0;
+    for line in source.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if let Some(key_len) = key_len(trimmed, role) {
+            let role_end = line_start + (line.len() - trimmed.len()) + key_len;
+            let offset = find_name(&source[role_end..], needle)? + role_end;
+            return Some(line_at(source, offset));
+        }
+        line_start += line.len();
+    }
+    None
+}
+
+/// Length of the table header or key that introduces `role` at the start
+/// of `line`, if it does
+fn key_len(line: &str, role: &str) -> Option<usize> {
+    let unquote = |key: &str| key.trim().trim_matches(|c| c == '"' || c == '\'').to_string();
+    if let Some(header) = line.strip_prefix('[') {
+        let end = header.find(']')?;
+        let last = header[..end].rsplit('.').next().map(unquote)?;
+        return (last == role).then_some(end + 2);
+    }
+
+    ["\"", "'", ""].into_iter().find_map(|quote| {
+        let rest = line.strip_prefix(quote)?.strip_prefix(role)?.strip_prefix(quote)?;
+        let separator = rest.trim_start();
+        (separator.starts_with(':') || separator.starts_with('=')).then(|| line.len() - separator.len() + 1)
+    })
+}
+
+/// Byte offset of the first mention of `name` that is not part of a
+/// longer name
+fn find_name(haystack: &str, name: &str) -> Option<usize> {
+    let is_name_char = |c: char| c.is_alphanumeric() || matches!(c, '_' | '-' | ':' | '*');
+    haystack.match_indices(name).map(|(offset, _)| offset).find(|&offset| {
+        let before = haystack[..offset].chars().next_back();
+        let after = haystack[offset + name.len()..].chars().next();
+        !before.is_some_and(is_name_char) && !after.is_some_and(is_name_char)
+    })
+}
+
+/// 1-based line number of a byte offset
+fn line_at(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
@@ -0,0 +1,210 @@
+//! Token extraction from incoming requests
+//!
+//! A [`TokenExtractor`] pulls the raw authentication token out of a request.
+//! Extractors are tried in order by an [`ExtractorChain`]; the first one that
+//! finds a credential wins. The default chain only accepts
+//! `Authorization: Bearer <token>`.
+
+use rocket::request::Request;
+
+/// Error returned when a credential is present but cannot be used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtractionError(pub String);
+
+impl std::fmt::Display for ExtractionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ExtractionError {}
+
+/// The `TokenExtractor` trait is implemented by anything that can find an
+/// authentication token in a request.
+pub trait TokenExtractor: Send + Sync + 'static {
+    /// Extracts the token from the request
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(token))` - A token was found
+    /// * `Ok(None)` - This extractor's credential is absent, so the next
+    ///   extractor in the chain is tried
+    /// * `Err(error)` - A credential is present but malformed, which stops
+    ///   the chain
+    fn extract(&self, request: &Request<'_>) -> Result<Option<String>, ExtractionError>;
+}
+
+/// Extracts a token from `Authorization: Bearer <token>`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BearerExtractor;
+
+impl TokenExtractor for BearerExtractor {
+    fn extract(&self, request: &Request<'_>) -> Result<Option<String>, ExtractionError> {
+        let auth_header = match request.headers().get_one("Authorization") {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+
+        match auth_header.strip_prefix("Bearer ") {
+            Some(token) => Ok(Some(token.to_string())),
+            None => Err(ExtractionError("Invalid authorization format".to_string())),
+        }
+    }
+}
+
+/// Extracts a token from a custom header such as `X-Api-Key`
+#[derive(Debug, Clone)]
+pub struct HeaderExtractor {
+    name: String,
+    prefix: Option<String>,
+}
+
+impl HeaderExtractor {
+    /// Create an extractor that uses the whole value of the given header
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            prefix: None,
+        }
+    }
+
+    /// Require the header value to start with the given prefix, which is
+    /// stripped from the token
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+}
+
+impl TokenExtractor for HeaderExtractor {
+    fn extract(&self, request: &Request<'_>) -> Result<Option<String>, ExtractionError> {
+        let value = match request.headers().get_one(&self.name) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        match &self.prefix {
+            Some(prefix) => match value.strip_prefix(prefix.as_str()) {
+                Some(token) => Ok(Some(token.to_string())),
+                None => Err(ExtractionError(format!("Invalid {} header format", self.name))),
+            },
+            None => Ok(Some(value.to_string())),
+        }
+    }
+}
+
+/// Extracts a token from a cookie
+#[derive(Debug, Clone)]
+pub struct CookieExtractor {
+    name: String,
+    #[cfg(feature = "secrets")]
+    private: bool,
+}
+
+impl CookieExtractor {
+    /// Create an extractor that reads the given plain cookie
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            #[cfg(feature = "secrets")]
+            private: false,
+        }
+    }
+
+    /// Create an extractor that reads the given private (encrypted) cookie
+    ///
+    /// Requires the `secrets` feature.
+    #[cfg(feature = "secrets")]
+    pub fn private(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            private: true,
+        }
+    }
+}
+
+impl TokenExtractor for CookieExtractor {
+    fn extract(&self, request: &Request<'_>) -> Result<Option<String>, ExtractionError> {
+        #[cfg(feature = "secrets")]
+        if self.private {
+            return Ok(request
+                .cookies()
+                .get_private(&self.name)
+                .map(|cookie| cookie.value().to_string()));
+        }
+
+        Ok(request
+            .cookies()
+            .get(&self.name)
+            .map(|cookie| cookie.value().to_string()))
+    }
+}
+
+/// Extracts a token from a query parameter such as `?access_token=`
+#[derive(Debug, Clone)]
+pub struct QueryExtractor {
+    name: String,
+}
+
+impl QueryExtractor {
+    /// Create an extractor that reads the given query parameter
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+impl TokenExtractor for QueryExtractor {
+    fn extract(&self, request: &Request<'_>) -> Result<Option<String>, ExtractionError> {
+        match request.query_value::<String>(&self.name) {
+            Some(Ok(token)) => Ok(Some(token)),
+            Some(Err(_)) => Err(ExtractionError(format!("Invalid {} query parameter", self.name))),
+            None => Ok(None),
+        }
+    }
+}
+
+/// An ordered list of extractors, tried until one finds a token
+pub struct ExtractorChain {
+    extractors: Vec<Box<dyn TokenExtractor>>,
+}
+
+impl ExtractorChain {
+    /// Create an empty chain
+    pub fn new() -> Self {
+        Self {
+            extractors: Vec::new(),
+        }
+    }
+
+    /// Append an extractor to the end of the chain
+    pub fn with(mut self, extractor: impl TokenExtractor) -> Self {
+        self.extractors.push(Box::new(extractor));
+        self
+    }
+
+    /// Run the extractors in order and return the first token found
+    pub fn extract(&self, request: &Request<'_>) -> Result<Option<String>, ExtractionError> {
+        for extractor in &self.extractors {
+            if let Some(token) = extractor.extract(request)? {
+                return Ok(Some(token));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl Default for ExtractorChain {
+    /// The default chain only accepts `Authorization: Bearer <token>`
+    fn default() -> Self {
+        Self::new().with(BearerExtractor)
+    }
+}
+
+impl std::fmt::Debug for ExtractorChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExtractorChain")
+            .field("len", &self.extractors.len())
+            .finish()
+    }
+}
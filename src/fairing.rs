@@ -0,0 +1,207 @@
+//! Rocket fairing that stores authentication configuration in managed state
+//!
+//! Attaching [`RocketRoles`] scopes the auth provider, roles and token
+//! extractors to a single Rocket instance, so several instances with
+//! different configurations can run in one process. The fairing also adds
+//! `WWW-Authenticate` challenges and `Retry-After` headers to rejected
+//! responses. Instances without the fairing fall back to the globals set by
+//! [`register_auth_provider`](crate::auth::register_auth_provider) and
+//! [`register_roles`](crate::auth::register_roles).
+//!
+//! Attach the fairing once per instance; ignition fails if it is attached
+//! twice.
+
+use crate::audit::AuditSink;
+use crate::auth::{AuthProvider, Role};
+use crate::config::{build_roles, RoleConfigError, RoleSpec};
+use crate::extract::ExtractorChain;
+use crate::registry::RoleRegistry;
+use crate::rejection::add_headers;
+use rocket::fairing::{self, Fairing, Info, Kind};
+use rocket::{Build, Request, Response, Rocket};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Fairing that registers an auth provider and roles with a Rocket instance
+///
+/// # Example
+///
+/// ```rust,ignore
+/// rocket::build()
+///     .attach(RocketRoles::new(MyAuthProvider::new(), roles()))
+///     .mount("/", routes![/* your routes */])
+/// ```
+#[derive(Clone)]
+pub struct RocketRoles {
+    pub(crate) provider: Arc<dyn AuthProvider>,
+    pub(crate) named_providers: HashMap<String, Arc<dyn AuthProvider>>,
+    pub(crate) registry: Arc<RoleRegistry>,
+    pub(crate) extractors: Arc<ExtractorChain>,
+    pub(crate) realm: Option<String>,
+    pub(crate) lenient_optional_auth: bool,
+    pub(crate) audit_sinks: Vec<Arc<dyn AuditSink>>,
+    roles_from_config: bool,
+}
+
+impl RocketRoles {
+    /// Create the fairing from an auth provider and a map of role names to
+    /// their permissions
+    ///
+    /// Role inheritance is resolved here, the same way
+    /// [`register_roles`](crate::auth::register_roles) does.
+    pub fn new(provider: impl AuthProvider, roles: HashMap<String, Role>) -> Self {
+        Self::with_registry(provider, Arc::new(RoleRegistry::from_roles(roles)))
+    }
+
+    /// Create the fairing from an auth provider and an existing role
+    /// registry, which can be changed while the application is running
+    pub fn with_registry(provider: impl AuthProvider, registry: Arc<RoleRegistry>) -> Self {
+        Self {
+            provider: Arc::new(provider),
+            named_providers: HashMap::new(),
+            registry,
+            extractors: Arc::new(ExtractorChain::default()),
+            realm: None,
+            lenient_optional_auth: false,
+            audit_sinks: Vec::new(),
+            roles_from_config: false,
+        }
+    }
+
+    /// Create the fairing from an auth provider, reading roles from the
+    /// `roles` key of the Rocket configuration when the instance ignites
+    ///
+    /// With this, a `Rocket.toml` such as the following just works:
+    ///
+    /// ```toml
+    /// [default.roles.user]
+    /// permissions = ["view_profile", "edit_profile"]
+    ///
+    /// [default.roles.admin]
+    /// parents = ["user"]
+    /// permissions = ["create_user"]
+    /// ```
+    ///
+    /// Ignition fails if the roles are missing or invalid.
+    pub fn from_config(provider: impl AuthProvider) -> Self {
+        let mut fairing = Self::with_registry(provider, Arc::new(RoleRegistry::new()));
+        fairing.roles_from_config = true;
+        fairing
+    }
+
+    /// Add a provider under a name, for routes that select it with
+    /// `User<P>` or the `provider` option of the attribute macros
+    ///
+    /// The provider passed to the constructor stays the default, used by
+    /// every route that does not select another one. A provider added here
+    /// takes precedence over one registered globally under the same name
+    /// with [`register_named_auth_provider`](crate::auth::register_named_auth_provider).
+    pub fn with_named_provider(mut self, name: impl Into<String>, provider: impl AuthProvider) -> Self {
+        self.named_providers.insert(name.into(), Arc::new(provider));
+        self
+    }
+
+    /// Use the given chain of extractors to find the token in each request
+    pub fn with_extractors(mut self, extractors: ExtractorChain) -> Self {
+        self.extractors = Arc::new(extractors);
+        self
+    }
+
+    /// Name the protection space in `WWW-Authenticate` challenges, e.g.
+    /// `Bearer realm="api"`
+    pub fn with_realm(mut self, realm: impl Into<String>) -> Self {
+        self.realm = Some(realm.into());
+        self
+    }
+
+    /// Treat requests with an invalid credential as anonymous in
+    /// [`MaybeUser`](crate::auth::MaybeUser) guards, instead of rejecting
+    /// them
+    ///
+    /// Useful for public pages that should keep working when a stale token
+    /// is still sent along. The [`User`](crate::auth::User) guard is not
+    /// affected.
+    pub fn with_lenient_optional_auth(mut self) -> Self {
+        self.lenient_optional_auth = true;
+        self
+    }
+
+    /// Report every authentication and authorization decision to the sink
+    ///
+    /// May be called several times to report to several sinks. See
+    /// [`audit`](crate::audit) for the built-in sinks.
+    ///
+    /// Auditing needs the fairing: applications that only register a
+    /// provider with [`register_auth_provider`](crate::auth::register_auth_provider)
+    /// have nowhere to register a sink, and record nothing.
+    pub fn with_audit_sink(mut self, sink: impl AuditSink) -> Self {
+        self.audit_sinks.push(Arc::new(sink));
+        self
+    }
+
+    /// The role registry managed by this fairing
+    pub fn registry(&self) -> &Arc<RoleRegistry> {
+        &self.registry
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for RocketRoles {
+    fn info(&self) -> Info {
+        Info {
+            name: "Rocket Roles",
+            kind: Kind::Ignite | Kind::Response,
+        }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        // Managing a second configuration would panic; fail with a clear
+        // message instead
+        if rocket.state::<RocketRoles>().is_some() {
+            rocket::error!("RocketRoles is attached more than once; attach a single, fully configured instance");
+            return Err(rocket);
+        }
+
+        if self.roles_from_config {
+            let roles = rocket
+                .figment()
+                .extract_inner::<HashMap<String, RoleSpec>>("roles")
+                .map_err(|e| RoleConfigError {
+                    line: None,
+                    message: e.to_string(),
+                })
+                .and_then(|specs| build_roles(specs, None));
+
+            match roles {
+                Ok(roles) => self.registry.replace_all(roles),
+                Err(e) => {
+                    rocket::error!("Invalid roles configuration: {}", e);
+                    return Err(rocket);
+                }
+            }
+        }
+
+        Ok(rocket.manage(self.clone()))
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        // Guards cannot set response headers, so challenges and retry hints
+        // for their rejections are added here, even when no catcher renders
+        // them
+        add_headers(request, response);
+    }
+}
+
+impl std::fmt::Debug for RocketRoles {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RocketRoles")
+            .field("named_providers", &self.named_providers.keys().collect::<Vec<_>>())
+            .field("registry", &self.registry)
+            .field("extractors", &self.extractors)
+            .field("realm", &self.realm)
+            .field("lenient_optional_auth", &self.lenient_optional_auth)
+            .field("audit_sinks", &self.audit_sinks.len())
+            .field("roles_from_config", &self.roles_from_config)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,321 @@
+//! JWKS-backed key resolution for the JWT provider
+//!
+//! OIDC identity providers publish their signing keys as a JSON Web Key Set
+//! and rotate them over time. [`JwksKeySource`] caches those keys by `kid`
+//! and refreshes the set when a token references an unknown `kid`, at most
+//! once per refresh interval. Requires the `jwt` feature; fetching over HTTP
+//! additionally requires the `jwks` feature.
+//!
+//! Keys that cannot be used to verify signatures, such as encryption keys or
+//! key types this crate does not support, are skipped rather than failing
+//! the whole set.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use rocket_roles::jwks::JwksKeySource;
+//! use rocket_roles::jwt::JwtAuthProvider;
+//! use jsonwebtoken::Algorithm;
+//!
+//! let keys = JwksKeySource::from_url("https://sso.example.com/.well-known/jwks.json");
+//! let provider = JwtAuthProvider::from_key_source(keys, &[Algorithm::RS256, Algorithm::ES256])
+//!     .with_issuer("https://sso.example.com");
+//! ```
+
+use crate::auth::AuthError;
+use crate::jwt::KeySource;
+use async_trait::async_trait;
+use jsonwebtoken::jwk::{Jwk, JwkSet, PublicKeyUse};
+use jsonwebtoken::{DecodingKey, Header};
+use rocket::tokio::sync::Mutex;
+use rocket::tokio::time::Instant;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{PoisonError, RwLock};
+use std::time::Duration;
+
+/// Default minimum time between two refreshes of the key set
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The `JwksFetcher` trait loads a JSON Web Key Set from somewhere
+#[async_trait]
+pub trait JwksFetcher: Send + Sync + 'static {
+    /// Fetches the current key set
+    async fn fetch(&self) -> Result<JwkSet, AuthError>;
+}
+
+/// Reads a JSON Web Key Set from a local file on every refresh
+#[derive(Debug, Clone)]
+pub struct FileJwksFetcher {
+    path: PathBuf,
+}
+
+impl FileJwksFetcher {
+    /// Create a fetcher for the given file
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl JwksFetcher for FileJwksFetcher {
+    async fn fetch(&self) -> Result<JwkSet, AuthError> {
+        let contents = rocket::tokio::fs::read_to_string(&self.path)
+            .await
+            .map_err(|e| AuthError::unavailable(format!("Failed to read {}", self.path.display()), e))?;
+        parse_jwks(&contents)
+    }
+}
+
+/// Fetches a JSON Web Key Set from an HTTP endpoint
+///
+/// Requires the `jwks` feature.
+#[cfg(feature = "jwks")]
+#[derive(Debug, Clone)]
+pub struct HttpJwksFetcher {
+    url: String,
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "jwks")]
+impl HttpJwksFetcher {
+    /// Create a fetcher for the given URL
+    pub fn new(url: impl Into<String>) -> Self {
+        Self::with_client(url, reqwest::Client::new())
+    }
+
+    /// Create a fetcher for the given URL that uses an existing client
+    pub fn with_client(url: impl Into<String>, client: reqwest::Client) -> Self {
+        Self {
+            url: url.into(),
+            client,
+        }
+    }
+}
+
+#[cfg(feature = "jwks")]
+#[async_trait]
+impl JwksFetcher for HttpJwksFetcher {
+    async fn fetch(&self) -> Result<JwkSet, AuthError> {
+        let response = self
+            .client
+            .get(&self.url)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| AuthError::unavailable(format!("Failed to fetch JWKS from {}", self.url), e))?;
+        let contents = response
+            .text()
+            .await
+            .map_err(|e| AuthError::unavailable(format!("Failed to fetch JWKS from {}", self.url), e))?;
+        parse_jwks(&contents)
+    }
+}
+
+/// Parse a JSON Web Key Set document, skipping keys that cannot be parsed
+fn parse_jwks(contents: &str) -> Result<JwkSet, AuthError> {
+    #[derive(Deserialize)]
+    struct RawSet {
+        keys: Vec<serde_json::Value>,
+    }
+
+    let raw: RawSet = serde_json::from_str(contents).map_err(|e| AuthError::unavailable("Invalid JWKS", e))?;
+    let keys = raw
+        .keys
+        .into_iter()
+        .filter_map(|key| {
+            let kid = key.get("kid").and_then(serde_json::Value::as_str).unwrap_or("").to_string();
+            serde_json::from_value::<Jwk>(key)
+                .map_err(|e| skipped_key(&kid, &e))
+                .ok()
+        })
+        .collect();
+    Ok(JwkSet { keys })
+}
+
+/// Log a key of the set that cannot be used
+fn skipped_key(kid: &str, reason: &dyn std::fmt::Display) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(kid, %reason, "skipping unusable JWK");
+    #[cfg(not(feature = "tracing"))]
+    rocket::warn!("Skipping unusable JWK '{}': {}", kid, reason);
+}
+
+/// When the key set was last fetched, and the error if that fetch failed
+#[derive(Default)]
+struct Refresh {
+    at: Option<Instant>,
+    error: Option<AuthError>,
+}
+
+/// Key source backed by a JSON Web Key Set
+pub struct JwksKeySource {
+    fetcher: Option<Box<dyn JwksFetcher>>,
+    keys: RwLock<HashMap<String, DecodingKey>>,
+    last_refresh: Mutex<Refresh>,
+    refresh_interval: Duration,
+}
+
+impl JwksKeySource {
+    /// Create a key source that loads keys with the given fetcher
+    ///
+    /// Keys are fetched on the first token, and again whenever a token
+    /// references an unknown `kid`.
+    pub fn new(fetcher: impl JwksFetcher) -> Self {
+        Self {
+            fetcher: Some(Box::new(fetcher)),
+            keys: RwLock::new(HashMap::new()),
+            last_refresh: Mutex::new(Refresh::default()),
+            refresh_interval: DEFAULT_REFRESH_INTERVAL,
+        }
+    }
+
+    /// Create a key source that fetches keys from the given URL
+    ///
+    /// Requires the `jwks` feature.
+    #[cfg(feature = "jwks")]
+    pub fn from_url(url: impl Into<String>) -> Self {
+        Self::new(HttpJwksFetcher::new(url))
+    }
+
+    /// Create a key source that reads keys from the given local JWKS file
+    pub fn from_file(path: impl Into<PathBuf>) -> Self {
+        Self::new(FileJwksFetcher::new(path))
+    }
+
+    /// Create a key source with a fixed key set that is never refreshed
+    pub fn from_jwks_str(contents: &str) -> Result<Self, AuthError> {
+        let source = Self {
+            fetcher: None,
+            keys: RwLock::new(HashMap::new()),
+            last_refresh: Mutex::new(Refresh::default()),
+            refresh_interval: DEFAULT_REFRESH_INTERVAL,
+        };
+        source.store(&parse_jwks(contents)?)?;
+        Ok(source)
+    }
+
+    /// Refresh the key set at most this often (default 60 seconds)
+    pub fn with_refresh_interval(mut self, interval: Duration) -> Self {
+        self.refresh_interval = interval;
+        self
+    }
+
+    /// Fetch the key set now, regardless of the refresh interval
+    pub async fn refresh(&self) -> Result<(), AuthError> {
+        let mut last_refresh = self.last_refresh.lock().await;
+        self.fetch_and_store(&mut last_refresh).await
+    }
+
+    /// Fetch and store the key set, remembering when and whether it failed
+    async fn fetch_and_store(&self, refresh: &mut Refresh) -> Result<(), AuthError> {
+        let Some(fetcher) = &self.fetcher else {
+            return Ok(());
+        };
+        let result = match fetcher.fetch().await {
+            Ok(set) => self.store(&set),
+            Err(e) => Err(e),
+        };
+        refresh.at = Some(Instant::now());
+        refresh.error = result.as_ref().err().cloned();
+        result
+    }
+
+    /// Replace the cached keys with the usable keys of the given set
+    ///
+    /// Fails, keeping the previous keys, if none of them is usable.
+    fn store(&self, set: &JwkSet) -> Result<(), AuthError> {
+        let mut keys = HashMap::new();
+        for (index, jwk) in set.keys.iter().enumerate() {
+            // Keys without a kid can only be used by tokens without one
+            let kid = jwk.common.key_id.clone().unwrap_or_else(|| format!("#{}", index));
+            if jwk.common.public_key_use == Some(PublicKeyUse::Encryption) {
+                skipped_key(&kid, &"encryption key");
+                continue;
+            }
+            match DecodingKey::from_jwk(jwk) {
+                Ok(key) => {
+                    keys.insert(kid, key);
+                }
+                Err(e) => skipped_key(&kid, &e),
+            }
+        }
+        if keys.is_empty() {
+            return Err(AuthError::ProviderUnavailable {
+                message: "JWKS has no usable keys".to_string(),
+                source: None,
+            });
+        }
+
+        *self.keys.write().unwrap_or_else(PoisonError::into_inner) = keys;
+        Ok(())
+    }
+
+    /// Look up a cached key
+    ///
+    /// A token without a `kid` matches the only key of a single-key set.
+    fn cached(&self, kid: Option<&str>) -> Option<DecodingKey> {
+        let keys = self.keys.read().unwrap_or_else(PoisonError::into_inner);
+        match kid {
+            Some(kid) => keys.get(kid).cloned(),
+            None if keys.len() == 1 => keys.values().next().cloned(),
+            None => None,
+        }
+    }
+}
+
+#[async_trait]
+impl KeySource for JwksKeySource {
+    async fn key(&self, header: &Header) -> Result<DecodingKey, AuthError> {
+        let kid = header.kid.as_deref();
+        if let Some(key) = self.cached(kid) {
+            return Ok(key);
+        }
+
+        if self.fetcher.is_some() {
+            // Only one task refreshes at a time; the others wait and then
+            // find the key in the cache
+            let mut last_refresh = self.last_refresh.lock().await;
+            if let Some(key) = self.cached(kid) {
+                return Ok(key);
+            }
+
+            let may_refresh = match last_refresh.at {
+                Some(at) => at.elapsed() >= self.refresh_interval,
+                None => true,
+            };
+            if may_refresh {
+                self.fetch_and_store(&mut last_refresh).await?;
+                if let Some(key) = self.cached(kid) {
+                    return Ok(key);
+                }
+            } else if let Some(error) = &last_refresh.error {
+                // The key set could not be fetched; until the next attempt,
+                // report that rather than an unknown key
+                return Err(error.clone());
+            }
+        }
+
+        Err(AuthError::InvalidToken(match kid {
+            Some(kid) => format!("Unknown key id '{}'", kid),
+            None => "Token has no key id".to_string(),
+        }))
+    }
+}
+
+impl std::fmt::Debug for JwksKeySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kids: Vec<String> = self
+            .keys
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .keys()
+            .cloned()
+            .collect();
+        f.debug_struct("JwksKeySource")
+            .field("kids", &kids)
+            .field("refresh_interval", &self.refresh_interval)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,291 @@
+//! Built-in JWT authentication provider
+//!
+//! [`JwtAuthProvider`] verifies HS256, RS256 and ES256 signed tokens, checks
+//! the `exp`, `nbf`, `iss` and `aud` claims, and maps configurable claims
+//! into a [`User`]. Requires the `jwt` feature.
+//!
+//! Verification keys come from a [`KeySource`]: either a single fixed key,
+//! or a [`JwksKeySource`](crate::jwks::JwksKeySource) for identity
+//! providers that rotate keys.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use rocket_roles::jwt::{ClaimMapping, JwtAuthProvider};
+//! use std::time::Duration;
+//!
+//! let provider = JwtAuthProvider::rs256_pem(include_bytes!("public.pem"))?
+//!     .with_issuer("https://sso.example.com")
+//!     .with_audience("my-api")
+//!     .with_leeway(Duration::from_secs(30))
+//!     .with_claims(ClaimMapping::default().roles("realm_access.roles"));
+//! ```
+
+use crate::auth::{AuthError, AuthProvider, User};
+use async_trait::async_trait;
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{Algorithm, DecodingKey, Header, Validation};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The `KeySource` trait resolves the key that verifies a token's signature
+#[async_trait]
+pub trait KeySource: Send + Sync + 'static {
+    /// Returns the key for a token with the given header
+    async fn key(&self, header: &Header) -> Result<DecodingKey, AuthError>;
+}
+
+/// A key source that always returns the same key
+#[derive(Clone)]
+pub struct StaticKey(pub DecodingKey);
+
+#[async_trait]
+impl KeySource for StaticKey {
+    async fn key(&self, _header: &Header) -> Result<DecodingKey, AuthError> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Describes which claims hold the user's identity, roles, permissions and
+/// OAuth2 scopes
+///
+/// Claim names may be dotted paths into nested objects, such as
+/// `realm_access.roles`. Role and permission claims may hold either an
+/// array of strings or a single space-separated string, as the OAuth2
+/// `scope` claim does.
+#[derive(Debug, Clone)]
+pub struct ClaimMapping {
+    id: String,
+    username: String,
+    roles: Option<String>,
+    permissions: Option<String>,
+    scopes: Option<String>,
+}
+
+impl ClaimMapping {
+    /// Use the given claim as the user id (default `sub`)
+    pub fn id(mut self, claim: impl Into<String>) -> Self {
+        self.id = claim.into();
+        self
+    }
+
+    /// Use the given claim as the username (default `preferred_username`)
+    ///
+    /// The user id is used when the claim is absent.
+    pub fn username(mut self, claim: impl Into<String>) -> Self {
+        self.username = claim.into();
+        self
+    }
+
+    /// Read roles from the given claim (default `roles`)
+    pub fn roles(mut self, claim: impl Into<String>) -> Self {
+        self.roles = Some(claim.into());
+        self
+    }
+
+    /// Do not read roles from the token
+    pub fn without_roles(mut self) -> Self {
+        self.roles = None;
+        self
+    }
+
+    /// Read direct permissions from the given claim, such as `scope`
+    /// (default none)
+    pub fn permissions(mut self, claim: impl Into<String>) -> Self {
+        self.permissions = Some(claim.into());
+        self
+    }
+
+    /// Read OAuth2 scopes from the given claim (default `scope`)
+    pub fn scopes(mut self, claim: impl Into<String>) -> Self {
+        self.scopes = Some(claim.into());
+        self
+    }
+
+    /// Do not read OAuth2 scopes from the token
+    pub fn without_scopes(mut self) -> Self {
+        self.scopes = None;
+        self
+    }
+
+    /// Build a user from decoded claims
+    pub(crate) fn to_user(&self, claims: &Value) -> Result<User, AuthError> {
+        let id = match lookup(claims, &self.id) {
+            Some(Value::String(id)) => id.clone(),
+            Some(Value::Number(id)) => id.to_string(),
+            _ => {
+                return Err(AuthError::InvalidToken(format!("Missing '{}' claim", self.id)));
+            }
+        };
+
+        let username = match lookup(claims, &self.username) {
+            Some(Value::String(username)) => username.clone(),
+            _ => id.clone(),
+        };
+
+        let mut user = User::new(id, username);
+        if let Some(claim) = &self.roles {
+            user = user.with_roles(string_list(lookup(claims, claim)));
+        }
+        if let Some(claim) = &self.permissions {
+            user = user.with_permissions(string_list(lookup(claims, claim)));
+        }
+        if let Some(claim) = &self.scopes {
+            user = user.with_scopes(string_list(lookup(claims, claim)));
+        }
+
+        Ok(user)
+    }
+}
+
+impl Default for ClaimMapping {
+    fn default() -> Self {
+        Self {
+            id: "sub".to_string(),
+            username: "preferred_username".to_string(),
+            roles: Some("roles".to_string()),
+            permissions: None,
+            scopes: Some("scope".to_string()),
+        }
+    }
+}
+
+/// Look up a dotted claim path
+fn lookup<'a>(claims: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(claims, |value, key| value.get(key))
+}
+
+/// Read an array of strings or a space-separated string
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect(),
+        Some(Value::String(items)) => items.split_whitespace().map(str::to_string).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Auth provider that validates JSON Web Tokens
+pub struct JwtAuthProvider {
+    keys: Arc<dyn KeySource>,
+    validation: Validation,
+    claims: ClaimMapping,
+}
+
+impl JwtAuthProvider {
+    /// Validate tokens with keys from the given source
+    ///
+    /// Only tokens signed with one of the given algorithms are accepted.
+    pub fn from_key_source(keys: impl KeySource, algorithms: &[Algorithm]) -> Self {
+        let mut validation = Validation::new(algorithms.first().copied().unwrap_or_default());
+        validation.algorithms = algorithms.to_vec();
+        validation.validate_nbf = true;
+        validation.validate_aud = false;
+
+        Self {
+            keys: Arc::new(keys),
+            validation,
+            claims: ClaimMapping::default(),
+        }
+    }
+
+    fn new(key: DecodingKey, algorithm: Algorithm) -> Self {
+        Self::from_key_source(StaticKey(key), &[algorithm])
+    }
+
+    /// Validate HS256 tokens signed with the given shared secret
+    pub fn hs256(secret: &[u8]) -> Self {
+        Self::new(DecodingKey::from_secret(secret), Algorithm::HS256)
+    }
+
+    /// Validate RS256 tokens against the given PEM encoded RSA public key
+    pub fn rs256_pem(pem: &[u8]) -> Result<Self, AuthError> {
+        let key = DecodingKey::from_rsa_pem(pem)
+            .map_err(|e| AuthError::Other(format!("Invalid RSA public key: {}", e)))?;
+        Ok(Self::new(key, Algorithm::RS256))
+    }
+
+    /// Validate ES256 tokens against the given PEM encoded EC public key
+    pub fn es256_pem(pem: &[u8]) -> Result<Self, AuthError> {
+        let key = DecodingKey::from_ec_pem(pem)
+            .map_err(|e| AuthError::Other(format!("Invalid EC public key: {}", e)))?;
+        Ok(Self::new(key, Algorithm::ES256))
+    }
+
+    /// Require the `iss` claim to be the given issuer
+    pub fn with_issuer(mut self, issuer: impl ToString) -> Self {
+        self.validation.set_issuer(&[issuer]);
+        // Without this, tokens lacking the claim are not checked at all
+        self.validation.required_spec_claims.insert("iss".to_string());
+        self
+    }
+
+    /// Require the `aud` claim to contain the given audience
+    pub fn with_audience(mut self, audience: impl ToString) -> Self {
+        self.validation.set_audience(&[audience]);
+        self.validation.validate_aud = true;
+        self.validation.required_spec_claims.insert("aud".to_string());
+        self
+    }
+
+    /// Allow this much clock skew when checking `exp` and `nbf`
+    /// (default 60 seconds)
+    pub fn with_leeway(mut self, leeway: Duration) -> Self {
+        self.validation.leeway = leeway.as_secs();
+        self
+    }
+
+    /// Map claims into users with the given mapping
+    pub fn with_claims(mut self, claims: ClaimMapping) -> Self {
+        self.claims = claims;
+        self
+    }
+}
+
+#[async_trait]
+impl AuthProvider for JwtAuthProvider {
+    async fn authenticate_token(&self, token: &str) -> Result<User, AuthError> {
+        let header = jsonwebtoken::decode_header(token).map_err(map_jwt_error)?;
+        if !self.validation.algorithms.contains(&header.alg) {
+            return Err(AuthError::InvalidToken("Invalid algorithm".to_string()));
+        }
+
+        // The key must match the token's algorithm, so validate against
+        // that algorithm alone
+        let mut validation = self.validation.clone();
+        validation.algorithms = vec![header.alg];
+
+        let key = self.keys.key(&header).await?;
+        let data = jsonwebtoken::decode::<Value>(token, &key, &validation)
+            .map_err(map_jwt_error)?;
+        self.claims.to_user(&data.claims)
+    }
+}
+
+/// Convert a validation failure into an auth error
+pub(crate) fn map_jwt_error(error: jsonwebtoken::errors::Error) -> AuthError {
+    let message = match error.kind() {
+        ErrorKind::ExpiredSignature => return AuthError::Expired,
+        ErrorKind::ImmatureSignature => "Token is not valid yet".to_string(),
+        ErrorKind::InvalidIssuer => "Invalid issuer".to_string(),
+        ErrorKind::InvalidAudience => "Invalid audience".to_string(),
+        ErrorKind::InvalidSignature => "Invalid signature".to_string(),
+        ErrorKind::InvalidAlgorithm => "Invalid algorithm".to_string(),
+        ErrorKind::MissingRequiredClaim(claim) => format!("Missing required claim '{}'", claim),
+        _ => error.to_string(),
+    };
+    AuthError::InvalidToken(message)
+}
+
+impl std::fmt::Debug for JwtAuthProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JwtAuthProvider")
+            .field("validation", &self.validation)
+            .field("claims", &self.claims)
+            .finish_non_exhaustive()
+    }
+}
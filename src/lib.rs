@@ -29,7 +29,6 @@
 //! ```rust
 //! use rocket_roles::auth::{AuthProvider, AuthError, User};
 //! use async_trait::async_trait;
-//! use std::collections::HashSet;
 //!
 //! struct MyAuthProvider {
 //!     // Your database connection or client here
@@ -46,19 +45,16 @@
 //!             return Err(AuthError::InvalidToken("Token is invalid".into()));
 //!         }
 //!         
-//!         Ok(User {
-//!             id: "123".into(),
-//!             username: "john_doe".into(),
-//!             roles: vec!["user".into()],
-//!             permissions: HashSet::from_iter(vec!["custom_permission".into()]),
-//!         })
+//!         Ok(User::new("123", "john_doe")
+//!             .with_role("user")
+//!             .with_permission("custom_permission"))
 //!     }
 //! }
 //! ```
 //!
 //! ### 3. Register your auth provider
 //!
-//! ```rust
+//! ```rust,ignore
 //! use rocket_roles::auth::register_auth_provider;
 //!
 //! #[launch]
@@ -67,7 +63,7 @@
 //!     let auth_provider = MyAuthProvider::new();
 //!     
 //!     // Register it
-//!     register_auth_provider(auth_provider);
+//!     register_auth_provider(auth_provider).expect("auth provider registered once");
 //!     
 //!     // Initialize roles
 //!     initialize_roles();
@@ -76,11 +72,27 @@
 //! }
 //! ```
 //!
+//! Alternatively, attach the [`RocketRoles`] fairing to keep the provider and
+//! roles in Rocket managed state instead of process-wide globals. This lets
+//! several Rocket instances with different configurations share a process:
+//!
+//! ```rust,ignore
+//! use rocket_roles::RocketRoles;
+//!
+//! #[launch]
+//! fn rocket() -> _ {
+//!     rocket::build()
+//!         .attach(RocketRoles::new(MyAuthProvider::new(), defined_roles()))
+//!         .mount("/", routes![/* your routes */])
+//! }
+//! ```
+//!
 //! ### 4. Protect your routes
 //!
 //! ```rust
 //! use rocket_roles::{require_role, require_permission};
 //! use rocket::get;
+//! # rocket_roles::define_roles! { "admin" => ["edit_profile"] }
 //!
 //! // Require a specific role
 //! #[require_role("admin")]
@@ -95,13 +107,60 @@
 //! fn edit_profile() -> &'static str {
 //!     "Edit your profile here"
 //! }
+//! # fn main() {}
 //! ```
+//!
+//! The check runs in a request guard before the handler is called, so the
+//! handler keeps its own return type. The authenticated user is available
+//! in the handler body as `user`. The attribute must be placed above the
+//! route attribute.
 
+// Lets the macros' `rocket_roles::` paths resolve inside this crate's tests
+extern crate self as rocket_roles;
+
+pub mod audit;
 pub mod auth;
+pub mod cache;
+pub mod chain;
+pub mod config;
+pub mod extract;
+pub mod fairing;
+#[cfg(feature = "jwt")]
+pub mod jwks;
+#[cfg(feature = "jwt")]
+pub mod jwt;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod permission;
+pub mod policy;
+pub mod registry;
+pub mod rejection;
+pub mod telemetry;
+pub mod tenant;
 pub mod macros;
 
-pub use auth::{AuthProvider, AuthError, User, Role, Permission};
-pub use rocket_roles_macros::{define_roles, require_role, require_permission};
+#[cfg(test)]
+mod tests;
+#[cfg(all(test, feature = "check-names"))]
+use tests::declared::__rocket_roles_declared;
+
+pub use auth::{AuthProvider, AuthError, DefaultProvider, MaybeUser, NamedProvider, User, Role, Permission};
+pub use rocket_roles_macros::{define_roles, require_role, require_permission, require_scope};
 
 // Re-export for convenience
-pub use auth::{register_auth_provider, register_roles};
+pub use auth::{
+    register_auth_provider, register_auth_provider_with_extractors, register_named_auth_provider, register_roles,
+    role_registry, unregister_named_auth_provider, AlreadyRegistered,
+};
+pub use cache::CachedAuthProvider;
+pub use chain::ProviderChain;
+pub use config::{RoleConfigError, RoleSpec};
+pub use fairing::RocketRoles;
+pub use permission::PermissionSet;
+pub use policy::{Action, Authorize, Policy};
+pub use registry::{RoleChange, RoleRegistry};
+pub use rejection::AuthRejection;
+pub use tenant::TenantSource;
+pub use extract::{
+    BearerExtractor, CookieExtractor, ExtractorChain, HeaderExtractor, QueryExtractor, TokenExtractor,
+};
@@ -4,4 +4,53 @@
 //! that are re-exported from the macro crate.
 
 // Re-export macros
-pub use rocket_roles_macros::{define_roles, require_role, require_permission};
+pub use rocket_roles_macros::{define_roles, require_role, require_permission, require_scope};
+
+/// Error returned when parsing a name that was not declared with
+/// `define_roles!` into the generated `RoleName` or `PermissionName` enum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseNameError {
+    kind: &'static str,
+    name: String,
+}
+
+impl ParseNameError {
+    #[doc(hidden)]
+    pub fn new(kind: &'static str, name: &str) -> Self {
+        Self {
+            kind,
+            name: name.to_string(),
+        }
+    }
+
+    /// The name that failed to parse
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl std::fmt::Display for ParseNameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown {} '{}'", self.kind, self.name)
+    }
+}
+
+impl std::error::Error for ParseNameError {}
+
+/// Dependencies used by macro-generated code
+#[doc(hidden)]
+pub mod __private {
+    pub use rocket_roles_macros::__check_declared as check_declared;
+    pub use serde;
+
+    /// Implemented only by [`User`](crate::User), so the attribute macros can
+    /// tell it apart from an application type that is also named `User`
+    #[diagnostic::on_unimplemented(
+        message = "`{Self}` is not `rocket_roles::User`",
+        label = "the attribute macros replace this parameter with the authenticated user",
+        note = "refer to your own type by a path such as `models::User`, or rename it on import"
+    )]
+    pub trait RocketRolesUser {}
+
+    impl<P> RocketRolesUser for crate::User<P> {}
+}
@@ -0,0 +1,174 @@
+//! Prometheus metrics for authentication and authorization
+//!
+//! Enabled with the `metrics` feature. The following metrics are collected
+//! in a process-wide registry:
+//!
+//! - `rocket_roles_authentications_total{result}`: provider calls by
+//!   outcome, `success` or the [`AuthError::kind`] of the failure
+//! - `rocket_roles_provider_duration_seconds{provider}`: histogram of
+//!   provider call latencies, by the name of the provider, or `default` for
+//!   the unnamed one. Providers are only called once found under the name a
+//!   route selects, so the label takes no more values than there are
+//!   registered providers
+//! - `rocket_roles_checks_total{kind, decision}`: checks made by the
+//!   attribute macros, by requirement kind and decision
+//!
+//! Mount [`routes`] to serve them in the Prometheus text format:
+//!
+//! ```rust,ignore
+//! rocket::build()
+//!     .attach(RocketRoles::new(MyAuthProvider::new(), roles()))
+//!     .mount("/metrics", rocket_roles::metrics::routes())
+//! ```
+
+use crate::audit::Decision;
+use crate::auth::{AuthError, User};
+use once_cell::sync::Lazy;
+use rocket::http::ContentType;
+use rocket::{get, routes, Route};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// Upper bounds of the latency histogram buckets, in seconds
+const BUCKETS: [f64; 12] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+
+/// Latency observations, bucketed
+#[derive(Debug, Default)]
+struct Histogram {
+    /// Observations per bucket, not cumulative; the last one is `+Inf`
+    counts: [u64; BUCKETS.len() + 1],
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        let bucket = BUCKETS.iter().position(|&bound| seconds <= bound).unwrap_or(BUCKETS.len());
+        self.counts[bucket] += 1;
+        self.sum += seconds;
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    authentications: BTreeMap<&'static str, u64>,
+    checks: BTreeMap<(&'static str, &'static str), u64>,
+    latency: BTreeMap<&'static str, Histogram>,
+}
+
+/// A registry of authentication and authorization metrics
+///
+/// The crate records into the [`global`](Metrics::global) registry; create
+/// a separate one to record and render your own observations.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    state: Mutex<State>,
+}
+
+static GLOBAL: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+impl Metrics {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The registry the crate records into
+    pub fn global() -> &'static Metrics {
+        &GLOBAL
+    }
+
+    /// Record the outcome and latency of a call to the provider registered
+    /// under the given name, or to the unnamed one for `None`
+    pub fn observe_authentication(
+        &self,
+        provider: Option<&'static str>,
+        result: &Result<User, AuthError>,
+        elapsed: Duration,
+    ) {
+        let label = match result {
+            Ok(_) => "success",
+            Err(e) => e.kind(),
+        };
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        *state.authentications.entry(label).or_default() += 1;
+        state
+            .latency
+            .entry(provider.unwrap_or("default"))
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Record a check of the given kind, e.g. `role`
+    pub fn observe_check(&self, kind: &'static str, decision: Decision) {
+        let decision = match decision {
+            Decision::Allow => "allow",
+            Decision::Deny => "deny",
+            Decision::Anonymous => "anonymous",
+        };
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        *state.checks.entry((kind, decision)).or_default() += 1;
+    }
+
+    /// Render the metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut out = String::new();
+
+        out.push_str("# HELP rocket_roles_authentications_total Auth provider calls by result.\n");
+        out.push_str("# TYPE rocket_roles_authentications_total counter\n");
+        for (result, count) in &state.authentications {
+            let _ = writeln!(out, "rocket_roles_authentications_total{{result=\"{}\"}} {}", result, count);
+        }
+
+        out.push_str("# HELP rocket_roles_provider_duration_seconds Auth provider call latency.\n");
+        out.push_str("# TYPE rocket_roles_provider_duration_seconds histogram\n");
+        for (provider, latency) in &state.latency {
+            let mut cumulative = 0;
+            for (bound, count) in BUCKETS.iter().zip(&latency.counts) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "rocket_roles_provider_duration_seconds_bucket{{provider=\"{}\",le=\"{}\"}} {}",
+                    provider, bound, cumulative
+                );
+            }
+            cumulative += latency.counts[BUCKETS.len()];
+            let _ = writeln!(
+                out,
+                "rocket_roles_provider_duration_seconds_bucket{{provider=\"{}\",le=\"+Inf\"}} {}",
+                provider, cumulative
+            );
+            let _ = writeln!(out, "rocket_roles_provider_duration_seconds_sum{{provider=\"{}\"}} {}", provider, latency.sum);
+            let _ = writeln!(out, "rocket_roles_provider_duration_seconds_count{{provider=\"{}\"}} {}", provider, cumulative);
+        }
+
+        out.push_str("# HELP rocket_roles_checks_total Authorization checks by kind and decision.\n");
+        out.push_str("# TYPE rocket_roles_checks_total counter\n");
+        for ((kind, decision), count) in &state.checks {
+            let _ = writeln!(
+                out,
+                "rocket_roles_checks_total{{kind=\"{}\",decision=\"{}\"}} {}",
+                kind, decision, count
+            );
+        }
+
+        out
+    }
+}
+
+#[get("/")]
+fn metrics() -> (ContentType, String) {
+    let content_type = ContentType::new("text", "plain").with_params([("version", "0.0.4"), ("charset", "utf-8")]);
+    (content_type, Metrics::global().render())
+}
+
+/// Routes serving the global metrics in the Prometheus text format
+///
+/// Mount them wherever the scraper expects them, usually `/metrics`. They
+/// are not protected; mount them on an internal port or guard them
+/// yourself if the numbers are sensitive.
+pub fn routes() -> Vec<Route> {
+    routes![metrics]
+}
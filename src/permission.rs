@@ -0,0 +1,140 @@
+//! Wildcard and hierarchical permission matching
+//!
+//! Permissions are made of `:`-separated segments, such as `posts:edit` or
+//! `billing:invoices:read`. A grant may use `*` as a segment:
+//!
+//! - `posts:*` grants every permission below `posts`, such as `posts:edit`
+//!   and `posts:edit:own`, but not `posts` itself
+//! - `posts:*:read` grants `posts:drafts:read` and `posts:comments:read`
+//! - `*` grants every permission
+//!
+//! Roles are compiled into a [`PermissionSet`], a trie over segments, so a
+//! check takes time proportional to the number of segments rather than the
+//! number of grants.
+//!
+//! ```rust,ignore
+//! use rocket_roles::permission::PermissionSet;
+//!
+//! let set = PermissionSet::from_grants(["posts:*", "billing:invoices:read"]);
+//! assert!(set.matches("posts:edit"));
+//! assert!(!set.matches("billing:invoices:write"));
+//! ```
+
+use std::collections::HashMap;
+
+/// Separator between the segments of a permission
+pub const SEPARATOR: char = ':';
+
+/// Segment that matches any segment
+pub const WILDCARD: &str = "*";
+
+/// A node of the permission trie
+#[derive(Debug, Clone, Default)]
+struct Node {
+    children: HashMap<String, Node>,
+    /// Child for a `*` segment
+    wildcard: Option<Box<Node>>,
+    /// A grant ends at this node
+    terminal: bool,
+    /// A grant ends with `*` at this node, so it matches any remaining
+    /// segments
+    matches_rest: bool,
+}
+
+impl Node {
+    fn insert(&mut self, segments: &[&str]) {
+        match segments.split_first() {
+            None => self.terminal = true,
+            Some((&WILDCARD, rest)) => {
+                let child = self.wildcard.get_or_insert_with(Default::default);
+                if rest.is_empty() {
+                    child.matches_rest = true;
+                }
+                child.insert(rest);
+            }
+            Some((segment, rest)) => self.children.entry(segment.to_string()).or_default().insert(rest),
+        }
+    }
+
+    fn matches(&self, segments: &[&str]) -> bool {
+        let Some((first, rest)) = segments.split_first() else {
+            return self.terminal;
+        };
+
+        if let Some(child) = self.children.get(*first) {
+            if child.matches(rest) {
+                return true;
+            }
+        }
+        match &self.wildcard {
+            Some(child) => child.matches_rest || child.matches(rest),
+            None => false,
+        }
+    }
+}
+
+/// A set of permission grants, compiled for fast wildcard-aware lookups
+#[derive(Debug, Clone, Default)]
+pub struct PermissionSet {
+    root: Node,
+    len: usize,
+}
+
+impl PermissionSet {
+    /// Create an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compile a set from the given grants
+    pub fn from_grants<I>(grants: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut set = Self::new();
+        for grant in grants {
+            set.insert(grant.as_ref());
+        }
+        set
+    }
+
+    /// Add a grant, which may contain `*` segments
+    pub fn insert(&mut self, grant: &str) {
+        let segments: Vec<&str> = grant.split(SEPARATOR).collect();
+        self.root.insert(&segments);
+        self.len += 1;
+    }
+
+    /// Whether any grant in the set covers the permission
+    pub fn matches(&self, permission: &str) -> bool {
+        let segments: Vec<&str> = permission.split(SEPARATOR).collect();
+        self.root.matches(&segments)
+    }
+
+    /// Number of grants added to the set
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the set has no grants
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Whether a single grant, which may contain `*` segments, covers the
+/// permission
+pub fn grant_matches(grant: &str, permission: &str) -> bool {
+    let mut grant = grant.split(SEPARATOR).peekable();
+    let mut permission = permission.split(SEPARATOR);
+    loop {
+        match (grant.next(), permission.next()) {
+            (None, None) => return true,
+            (Some(WILDCARD), Some(_)) if grant.peek().is_none() => return true,
+            (Some(WILDCARD), Some(_)) => continue,
+            (Some(expected), Some(actual)) if expected == actual => continue,
+            _ => return false,
+        }
+    }
+}
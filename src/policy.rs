@@ -0,0 +1,122 @@
+//! Resource-scoped authorization
+//!
+//! Roles and permissions answer "may this user edit posts?". Policies answer
+//! "may this user edit *this* post?", e.g. authors may edit their own posts
+//! and moderators may edit any. Implement [`Authorize`] on the resource, or
+//! a [`Policy`] for it, and check it from the handler with [`User::can`]:
+//!
+//! ```rust,ignore
+//! use rocket_roles::policy::{Action, Authorize};
+//! use rocket_roles::rejection::AuthRejection;
+//!
+//! #[rocket::async_trait]
+//! impl Authorize for Post {
+//!     async fn authorize(&self, user: &User, action: &Action) -> bool {
+//!         match action {
+//!             Action::Read => true,
+//!             Action::Edit => self.author_id == user.id || user.has_permission("edit_any_post"),
+//!             _ => user.has_role("admin"),
+//!         }
+//!     }
+//! }
+//!
+//! #[put("/posts/<id>", data = "<body>")]
+//! async fn edit_post(id: u32, body: String, user: User) -> Result<&'static str, AuthRejection> {
+//!     let post = load_post(id).await;
+//!     user.can(Action::Edit, &post).await?;
+//!     Ok("updated")
+//! }
+//! ```
+//!
+//! A denied check returns [`AuthRejection::ActionDenied`], which responds
+//! with 403 and is rendered by the same catchers as the attribute macros.
+
+use crate::auth::User;
+use crate::rejection::AuthRejection;
+use async_trait::async_trait;
+
+/// An action performed on a resource
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// View the resource
+    Read,
+    /// Create the resource
+    Create,
+    /// Change the resource
+    Edit,
+    /// Delete the resource
+    Delete,
+    /// Any other action, such as `publish`
+    Custom(String),
+}
+
+impl Action {
+    /// Create a custom action
+    pub fn custom(name: impl Into<String>) -> Self {
+        Action::Custom(name.into())
+    }
+
+    /// The action's name, e.g. `edit`
+    pub fn name(&self) -> &str {
+        match self {
+            Action::Read => "read",
+            Action::Create => "create",
+            Action::Edit => "edit",
+            Action::Delete => "delete",
+            Action::Custom(name) => name,
+        }
+    }
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The `Authorize` trait is implemented by resources that decide which
+/// users may act on them
+#[async_trait]
+pub trait Authorize: Send + Sync {
+    /// Returns whether the user may perform the action on this resource
+    async fn authorize(&self, user: &User, action: &Action) -> bool;
+}
+
+/// The `Policy` trait decides which users may act on resources of type `R`
+///
+/// Use a policy instead of [`Authorize`] when the decision needs state the
+/// resource does not have, such as a database pool, or when the resource
+/// type is defined in another crate.
+#[async_trait]
+pub trait Policy<R: ?Sized + Sync>: Send + Sync {
+    /// Returns whether the user may perform the action on the resource
+    async fn allows(&self, user: &User, resource: &R, action: &Action) -> bool;
+}
+
+impl User {
+    /// Check that this user may perform the action on the resource
+    ///
+    /// Returns [`AuthRejection::ActionDenied`] otherwise, so the check can
+    /// be used with `?` in handlers that return `Result<_, AuthRejection>`.
+    pub async fn can<R: Authorize + ?Sized>(&self, action: Action, resource: &R) -> Result<(), AuthRejection> {
+        if resource.authorize(self, &action).await {
+            Ok(())
+        } else {
+            Err(AuthRejection::ActionDenied(action.to_string()))
+        }
+    }
+
+    /// Check that the policy lets this user perform the action on the
+    /// resource
+    pub async fn can_with<R, P>(&self, policy: &P, action: Action, resource: &R) -> Result<(), AuthRejection>
+    where
+        R: Sync + ?Sized,
+        P: Policy<R> + ?Sized,
+    {
+        if policy.allows(self, resource, &action).await {
+            Ok(())
+        } else {
+            Err(AuthRejection::ActionDenied(action.to_string()))
+        }
+    }
+}
@@ -0,0 +1,252 @@
+//! Runtime-mutable role registry
+//!
+//! A [`RoleRegistry`] holds the role definitions used by
+//! [`User::has_permission`](crate::auth::User::has_permission) and
+//! [`User::all_permissions`](crate::auth::User::all_permissions). Roles can be
+//! added, removed, granted and revoked permissions, or replaced wholesale
+//! while the application is running, and subscribers are notified of every
+//! change.
+
+use crate::auth::{resolve_role_hierarchy, Permission, Role};
+use crate::permission::PermissionSet;
+#[cfg(any(feature = "toml", feature = "json", feature = "yaml"))]
+use crate::config::RoleConfigError;
+use rocket::tokio::sync::broadcast;
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// Capacity of the change notification channel. Subscribers that fall
+/// further behind than this miss the oldest changes.
+const CHANGE_CHANNEL_CAPACITY: usize = 64;
+
+/// A change applied to a [`RoleRegistry`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoleChange {
+    /// A role was added or redefined
+    Added(String),
+    /// A role was removed
+    Removed(String),
+    /// A permission was granted to a role
+    Granted {
+        /// The role that was changed
+        role: String,
+        /// The permission that was granted
+        permission: Permission,
+    },
+    /// A permission was revoked from a role
+    Revoked {
+        /// The role that was changed
+        role: String,
+        /// The permission that was revoked
+        permission: Permission,
+    },
+    /// Every role was replaced at once
+    Replaced,
+}
+
+/// The roles as declared, together with their resolved inheritance closure
+/// and its compiled permission sets
+struct Snapshot {
+    declared: HashMap<String, Role>,
+    resolved: Arc<HashMap<String, Role>>,
+    compiled: Arc<HashMap<String, PermissionSet>>,
+}
+
+impl Snapshot {
+    fn new(declared: HashMap<String, Role>) -> Self {
+        let mut snapshot = Self {
+            declared,
+            resolved: Arc::default(),
+            compiled: Arc::default(),
+        };
+        snapshot.resolve();
+        snapshot
+    }
+
+    /// Re-resolve inheritance and recompile permission sets after a change
+    fn resolve(&mut self) {
+        let resolved = resolve_role_hierarchy(&self.declared);
+        let compiled = resolved
+            .iter()
+            .map(|(name, role)| (name.clone(), PermissionSet::from_grants(&role.permissions)))
+            .collect();
+        self.resolved = Arc::new(resolved);
+        self.compiled = Arc::new(compiled);
+    }
+}
+
+/// Thread-safe, runtime-mutable set of roles
+///
+/// Reads take a cheap snapshot of the resolved roles, so a permission check
+/// never observes a half-applied change. Every mutation re-resolves role
+/// inheritance before it becomes visible.
+pub struct RoleRegistry {
+    snapshot: RwLock<Snapshot>,
+    changes: broadcast::Sender<RoleChange>,
+}
+
+impl RoleRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::from_roles(HashMap::new())
+    }
+
+    /// Create a registry from a map of role names to their permissions
+    pub fn from_roles(roles: HashMap<String, Role>) -> Self {
+        let (changes, _) = broadcast::channel(CHANGE_CHANNEL_CAPACITY);
+        Self {
+            snapshot: RwLock::new(Snapshot::new(roles)),
+            changes,
+        }
+    }
+
+    /// Create a registry from a TOML document
+    ///
+    /// See the [`config`](crate::config) module for the expected layout.
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(source: &str) -> Result<Self, RoleConfigError> {
+        crate::config::roles_from_toml_str(source).map(Self::from_roles)
+    }
+
+    /// Create a registry from a JSON document
+    ///
+    /// See the [`config`](crate::config) module for the expected layout.
+    #[cfg(feature = "json")]
+    pub fn from_json(source: &str) -> Result<Self, RoleConfigError> {
+        crate::config::roles_from_json_str(source).map(Self::from_roles)
+    }
+
+    /// Create a registry from a YAML document
+    ///
+    /// See the [`config`](crate::config) module for the expected layout.
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(source: &str) -> Result<Self, RoleConfigError> {
+        crate::config::roles_from_yaml_str(source).map(Self::from_roles)
+    }
+
+    /// The current roles, with inheritance resolved
+    pub fn roles(&self) -> Arc<HashMap<String, Role>> {
+        self.snapshot
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .resolved
+            .clone()
+    }
+
+    /// The current roles' permissions, inherited ones included, compiled for
+    /// wildcard-aware lookups
+    pub fn permission_sets(&self) -> Arc<HashMap<String, PermissionSet>> {
+        self.snapshot
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .compiled
+            .clone()
+    }
+
+    /// The current roles as declared, without inherited permissions
+    pub fn declared_roles(&self) -> HashMap<String, Role> {
+        self.snapshot
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .declared
+            .clone()
+    }
+
+    /// Add a role, replacing any existing role with the same name
+    ///
+    /// Returns the role that was replaced, if any.
+    pub fn add_role(&self, role: Role) -> Option<Role> {
+        let name = role.name.clone();
+        let previous = self.update(|declared| declared.insert(name.clone(), role));
+        self.notify(RoleChange::Added(name));
+        previous
+    }
+
+    /// Remove a role
+    ///
+    /// Returns the removed role, or `None` if it did not exist.
+    pub fn remove_role(&self, name: &str) -> Option<Role> {
+        let removed = self.update(|declared| declared.remove(name));
+        if removed.is_some() {
+            self.notify(RoleChange::Removed(name.to_string()));
+        }
+        removed
+    }
+
+    /// Grant a permission to a role
+    ///
+    /// Returns `false` if the role does not exist or already had the
+    /// permission.
+    pub fn grant(&self, role: &str, permission: impl Into<Permission>) -> bool {
+        let permission = permission.into();
+        let granted = self.update(|declared| match declared.get_mut(role) {
+            Some(existing) => existing.permissions.insert(permission.clone()),
+            None => false,
+        });
+        if granted {
+            self.notify(RoleChange::Granted {
+                role: role.to_string(),
+                permission,
+            });
+        }
+        granted
+    }
+
+    /// Revoke a permission from a role
+    ///
+    /// Returns `false` if the role does not exist or did not have the
+    /// permission. Permissions inherited from a parent role must be revoked
+    /// from that parent.
+    pub fn revoke(&self, role: &str, permission: &str) -> bool {
+        let revoked = self.update(|declared| match declared.get_mut(role) {
+            Some(existing) => existing.permissions.remove(permission),
+            None => false,
+        });
+        if revoked {
+            self.notify(RoleChange::Revoked {
+                role: role.to_string(),
+                permission: permission.to_string(),
+            });
+        }
+        revoked
+    }
+
+    /// Atomically replace every role
+    pub fn replace_all(&self, roles: HashMap<String, Role>) {
+        let snapshot = Snapshot::new(roles);
+        *self.snapshot.write().unwrap_or_else(PoisonError::into_inner) = snapshot;
+        self.notify(RoleChange::Replaced);
+    }
+
+    /// Subscribe to changes applied to this registry
+    pub fn subscribe(&self) -> broadcast::Receiver<RoleChange> {
+        self.changes.subscribe()
+    }
+
+    /// Apply a change to the declared roles and re-resolve inheritance
+    fn update<T>(&self, change: impl FnOnce(&mut HashMap<String, Role>) -> T) -> T {
+        let mut snapshot = self.snapshot.write().unwrap_or_else(PoisonError::into_inner);
+        let result = change(&mut snapshot.declared);
+        snapshot.resolve();
+        result
+    }
+
+    fn notify(&self, change: RoleChange) {
+        // Sending only fails when nobody is subscribed
+        let _ = self.changes.send(change);
+    }
+}
+
+impl Default for RoleRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for RoleRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RoleRegistry")
+            .field("roles", &self.roles())
+            .finish()
+    }
+}
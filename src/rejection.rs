@@ -0,0 +1,344 @@
+//! Structured rejection responses
+//!
+//! When authentication or authorization fails, the reason is recorded as an
+//! [`AuthRejection`] in the request-local cache. The catchers in this module
+//! read it back and render it, either as plain JSON or as an RFC 7807
+//! `application/problem+json` document:
+//!
+//! ```rust,ignore
+//! rocket::build()
+//!     .register("/", rocket_roles::rejection::json_catchers())
+//!     .mount("/", routes![/* your routes */])
+//! ```
+//!
+//! Implement [`RejectionRenderer`] and pass it to [`catchers`] to render
+//! rejections in any other format.
+//!
+//! The catchers also add the `WWW-Authenticate` challenge and `Retry-After`
+//! headers. The [`RocketRoles`] fairing adds them too, but when the provider
+//! is registered globally the catchers are the only place they come from.
+
+use crate::auth::AuthError;
+use crate::fairing::RocketRoles;
+use rocket::catcher::{self, Catcher};
+use rocket::http::{ContentType, Status};
+use rocket::request::Request;
+use rocket::response::{self, Responder, Response};
+use rocket::serde::json::{json, Value};
+use std::io::Cursor;
+use std::sync::{Mutex, PoisonError};
+
+/// The reason a request was rejected by the auth guards
+///
+/// New variants may be added in minor releases, so matches outside this
+/// crate need a wildcard arm, e.g. one that falls back to [`code`](Self::code).
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum AuthRejection {
+    /// No credential was found in the request
+    MissingCredentials,
+    /// A credential was found but is malformed, e.g. a wrong header scheme
+    MalformedCredentials(String),
+    /// The auth provider rejected the token, or failed to check it
+    ///
+    /// The status follows [`AuthError::status`], so backend failures are
+    /// reported as 5xx rather than 401.
+    InvalidToken(AuthError),
+    /// No auth provider has been registered
+    ProviderUnregistered,
+    /// The user does not satisfy the required role
+    MissingRole(String),
+    /// The user does not satisfy the required permission
+    MissingPermission(String),
+    /// A policy denied the action on a resource
+    ActionDenied(String),
+    /// The route checks roles within a tenant, but the request does not
+    /// name one
+    MissingTenant(String),
+    /// The token was not granted the required OAuth2 scopes
+    InsufficientScope {
+        /// Human readable form of the requirement, as in [`MissingRole`](AuthRejection::MissingRole)
+        requirement: String,
+        /// The scopes the route asks for, reported in the
+        /// `WWW-Authenticate` challenge
+        scopes: Vec<String>,
+    },
+}
+
+impl AuthRejection {
+    /// The HTTP status this rejection maps to
+    pub fn status(&self) -> Status {
+        match self {
+            AuthRejection::MissingCredentials | AuthRejection::MalformedCredentials(_) => Status::Unauthorized,
+            AuthRejection::InvalidToken(error) => error.status(),
+            AuthRejection::ProviderUnregistered => Status::InternalServerError,
+            AuthRejection::MissingTenant(_) => Status::BadRequest,
+            AuthRejection::MissingRole(_)
+            | AuthRejection::MissingPermission(_)
+            | AuthRejection::ActionDenied(_)
+            | AuthRejection::InsufficientScope { .. } => Status::Forbidden,
+        }
+    }
+
+    /// A stable, machine-readable identifier for this kind of rejection
+    pub fn code(&self) -> &'static str {
+        match self {
+            AuthRejection::MissingCredentials => "missing_credentials",
+            AuthRejection::MalformedCredentials(_) => "malformed_credentials",
+            AuthRejection::InvalidToken(error) => error.code(),
+            AuthRejection::ProviderUnregistered => "provider_unregistered",
+            AuthRejection::MissingRole(_) => "missing_role",
+            AuthRejection::MissingPermission(_) => "missing_permission",
+            AuthRejection::ActionDenied(_) => "action_denied",
+            AuthRejection::MissingTenant(_) => "missing_tenant",
+            AuthRejection::InsufficientScope { .. } => "insufficient_scope",
+        }
+    }
+
+    /// The value of the `WWW-Authenticate` header to send with this
+    /// rejection, if any
+    ///
+    /// Challenges follow RFC 6750. A missing credential gets a bare
+    /// challenge, `Bearer realm="api"`; other failures add an error code:
+    ///
+    /// - `invalid_request` for malformed credentials
+    /// - `invalid_token` for rejected or expired tokens, with an
+    ///   `error_description`
+    /// - `insufficient_scope` for missing OAuth2 scopes, with the `scope`
+    ///   the route asks for
+    pub fn challenge(&self, realm: Option<&str>) -> Option<String> {
+        let mut params = Vec::new();
+        if let Some(realm) = realm {
+            params.push(("realm", realm.to_string()));
+        }
+
+        match self {
+            AuthRejection::MissingCredentials => {}
+            AuthRejection::MalformedCredentials(message) => {
+                params.push(("error", "invalid_request".to_string()));
+                params.push(("error_description", message.clone()));
+            }
+            AuthRejection::InvalidToken(error) if error.status() == Status::Unauthorized => {
+                let description = match error {
+                    AuthError::InvalidToken(message) => message.clone(),
+                    error => error.to_string(),
+                };
+                params.push(("error", "invalid_token".to_string()));
+                params.push(("error_description", description));
+            }
+            AuthRejection::InsufficientScope { scopes, .. } => {
+                params.push(("error", "insufficient_scope".to_string()));
+                params.push(("scope", scopes.join(" ")));
+            }
+            _ => return None,
+        }
+
+        let params: Vec<String> = params
+            .into_iter()
+            .map(|(name, value)| format!("{}=\"{}\"", name, quoted(&value)))
+            .collect();
+        if params.is_empty() {
+            Some("Bearer".to_string())
+        } else {
+            Some(format!("Bearer {}", params.join(", ")))
+        }
+    }
+
+    /// Record this rejection for the current request so catchers can render it
+    pub fn stash(&self, request: &Request<'_>) {
+        let slot = request.local_cache(|| RejectionSlot(Mutex::new(None)));
+        *slot.0.lock().unwrap_or_else(PoisonError::into_inner) = Some(self.clone());
+    }
+
+    /// The rejection recorded for the current request, if any
+    pub fn from_request(request: &Request<'_>) -> Option<AuthRejection> {
+        let slot = request.local_cache(|| RejectionSlot(Mutex::new(None)));
+        let rejection = slot.0.lock().unwrap_or_else(PoisonError::into_inner);
+        rejection.clone()
+    }
+}
+
+impl std::fmt::Display for AuthRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthRejection::MissingCredentials => write!(f, "Authentication token is required"),
+            AuthRejection::MalformedCredentials(msg) => write!(f, "{}", msg),
+            // Backend failures are described in the logs, not to clients
+            AuthRejection::InvalidToken(e) if e.status().class().is_server_error() => {
+                write!(f, "Authentication is temporarily unavailable")
+            }
+            AuthRejection::InvalidToken(e) => write!(f, "Authentication failed: {}", e),
+            AuthRejection::ProviderUnregistered => write!(f, "Auth provider not registered"),
+            AuthRejection::MissingRole(role) => write!(f, "Role {} required", role),
+            AuthRejection::MissingPermission(permission) => write!(f, "Permission {} required", permission),
+            AuthRejection::ActionDenied(action) => write!(f, "Not allowed to {} this resource", action),
+            AuthRejection::MissingTenant(source) => write!(f, "Tenant required from {}", source),
+            AuthRejection::InsufficientScope { requirement, .. } => write!(f, "Scope {} required", requirement),
+        }
+    }
+}
+
+impl std::error::Error for AuthRejection {}
+
+/// Returning a rejection from a handler records it and fails with its
+/// status, so it is rendered by the same catchers as the guards' rejections
+impl<'r> Responder<'r, 'static> for AuthRejection {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        self.stash(request);
+        Err(self.status())
+    }
+}
+
+/// Add the `WWW-Authenticate` challenge and `Retry-After` header of the
+/// request's rejection to a response with the rejection's status, unless
+/// they are already set
+pub(crate) fn add_headers(request: &Request<'_>, response: &mut Response<'_>) {
+    let Some(rejection) = AuthRejection::from_request(request) else {
+        return;
+    };
+    if rejection.status() != response.status() {
+        return;
+    }
+
+    let realm = request.rocket().state::<RocketRoles>().and_then(|state| state.realm.as_deref());
+    if let Some(challenge) = rejection.challenge(realm) {
+        if !response.headers().contains("WWW-Authenticate") {
+            response.set_raw_header("WWW-Authenticate", challenge);
+        }
+    }
+    if let AuthRejection::InvalidToken(AuthError::RateLimited { retry_after: Some(retry_after) }) = rejection {
+        if !response.headers().contains("Retry-After") {
+            // Round up, so clients never retry too early
+            let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+            response.set_raw_header("Retry-After", seconds.to_string());
+        }
+    }
+}
+
+/// Escape a value for use in a quoted-string header parameter
+fn quoted(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Request-local storage for the most recent rejection
+struct RejectionSlot(Mutex<Option<AuthRejection>>);
+
+/// Build a response with the given status, content type and JSON body
+fn json_response(status: Status, content_type: ContentType, body: Value) -> Response<'static> {
+    let body = body.to_string();
+    Response::build()
+        .status(status)
+        .header(content_type)
+        .sized_body(body.len(), Cursor::new(body))
+        .finalize()
+}
+
+/// The `RejectionRenderer` trait turns a rejection into a response
+pub trait RejectionRenderer: Clone + Send + Sync + 'static {
+    /// Renders the rejection recorded for the request
+    ///
+    /// `rejection` is `None` when the error did not come from the auth
+    /// guards, e.g. a 401 returned by a handler directly.
+    fn render(&self, status: Status, rejection: Option<&AuthRejection>, request: &Request<'_>) -> Response<'static>;
+}
+
+/// Renders rejections as `{"status": 403, "error": "missing_role", "message": "..."}`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonRenderer;
+
+impl RejectionRenderer for JsonRenderer {
+    fn render(&self, status: Status, rejection: Option<&AuthRejection>, _request: &Request<'_>) -> Response<'static> {
+        json_response(status, ContentType::JSON, json_body(status, rejection))
+    }
+}
+
+/// The body rendered by [`JsonRenderer`]
+fn json_body(status: Status, rejection: Option<&AuthRejection>) -> Value {
+    match rejection {
+        Some(rejection) => json!({
+            "status": status.code,
+            "error": rejection.code(),
+            "message": rejection.to_string(),
+        }),
+        None => json!({
+            "status": status.code,
+            "error": status.reason_lossy().to_lowercase().replace(' ', "_"),
+            "message": status.reason_lossy(),
+        }),
+    }
+}
+
+/// Renders rejections as RFC 7807 `application/problem+json` documents
+#[derive(Debug, Clone, Default)]
+pub struct ProblemJsonRenderer {
+    type_base: Option<String>,
+}
+
+impl ProblemJsonRenderer {
+    /// Create a renderer that uses `about:blank` as the problem type
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `<base><code>` as the problem type URI, e.g.
+    /// `https://example.com/problems/missing_role`
+    pub fn with_type_base(mut self, base: impl Into<String>) -> Self {
+        self.type_base = Some(base.into());
+        self
+    }
+}
+
+impl RejectionRenderer for ProblemJsonRenderer {
+    fn render(&self, status: Status, rejection: Option<&AuthRejection>, request: &Request<'_>) -> Response<'static> {
+        let problem_type = match (&self.type_base, rejection) {
+            (Some(base), Some(rejection)) => format!("{}{}", base, rejection.code()),
+            _ => "about:blank".to_string(),
+        };
+        let mut body = json!({
+            "type": problem_type,
+            "title": status.reason_lossy(),
+            "status": status.code,
+            "instance": request.uri().path().to_string(),
+        });
+        if let Some(rejection) = rejection {
+            body["detail"] = json!(rejection.to_string());
+            body["code"] = json!(rejection.code());
+        }
+        json_response(status, ContentType::new("application", "problem+json"), body)
+    }
+}
+
+/// Catcher handler that renders the recorded rejection
+#[derive(Clone)]
+struct RejectionCatcher<R: RejectionRenderer>(R);
+
+#[rocket::async_trait]
+impl<R: RejectionRenderer> catcher::Handler for RejectionCatcher<R> {
+    async fn handle<'r>(&self, status: Status, request: &'r Request<'_>) -> catcher::Result<'r> {
+        // A rejection stashed by a guard that did not fail the request, e.g.
+        // `MaybeUser`, does not explain an error the route returned itself
+        let rejection = AuthRejection::from_request(request).filter(|rejection| rejection.status() == status);
+        let mut response = self.0.render(status, rejection.as_ref(), request);
+        add_headers(request, &mut response);
+        Ok(response)
+    }
+}
+
+/// Catchers for 400, 401, 403, 429, 500 and 503 responses that render
+/// rejections with the given renderer
+pub fn catchers(renderer: impl RejectionRenderer) -> Vec<Catcher> {
+    [400, 401, 403, 429, 500, 503]
+        .into_iter()
+        .map(|code| Catcher::new(code, RejectionCatcher(renderer.clone())))
+        .collect()
+}
+
+/// Catchers that render rejections as plain JSON
+pub fn json_catchers() -> Vec<Catcher> {
+    catchers(JsonRenderer)
+}
+
+/// Catchers that render rejections as RFC 7807 problem details
+pub fn problem_catchers() -> Vec<Catcher> {
+    catchers(ProblemJsonRenderer::new())
+}
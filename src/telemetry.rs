@@ -0,0 +1,107 @@
+//! Instrumentation of authentication and authorization
+//!
+//! With the `tracing` feature, token extraction, auth provider calls and
+//! the checks generated by the attribute macros run in `tracing` spans:
+//!
+//! - `extract_token`, with whether a token was `found`
+//! - `authenticate_token`, with the `user_id` on success or the error
+//!   `kind` on failure
+//! - `check`, with the requirement `kind`, the `requirement` itself, the
+//!   `user_id` and whether the check was `allowed`
+//!
+//! Tokens are never recorded. With the `metrics` feature, the same places
+//! record into the [`metrics`](crate::metrics) registry.
+
+use crate::audit::AuditKind;
+use crate::auth::{AuthError, AuthProvider, User};
+use crate::extract::{ExtractionError, ExtractorChain};
+use rocket::request::Request;
+
+/// Run the extractor chain on the request
+pub(crate) fn extract_token(
+    extractors: &ExtractorChain,
+    request: &Request<'_>,
+) -> Result<Option<String>, ExtractionError> {
+    #[cfg(feature = "tracing")]
+    let span = tracing::debug_span!("extract_token", found = tracing::field::Empty).entered();
+
+    let token = extractors.extract(request);
+
+    #[cfg(feature = "tracing")]
+    span.record("found", matches!(token, Ok(Some(_))));
+
+    token
+}
+
+/// Ask the provider, registered under `name` unless it is the unnamed one,
+/// to authenticate the token
+pub(crate) async fn authenticate_token(
+    provider: &dyn AuthProvider,
+    name: Option<&'static str>,
+    token: &str,
+) -> Result<User, AuthError> {
+    #[cfg(feature = "metrics")]
+    let started = std::time::Instant::now();
+
+    #[cfg(feature = "tracing")]
+    let result = {
+        use tracing::Instrument;
+
+        let span = tracing::info_span!(
+            "authenticate_token",
+            user_id = tracing::field::Empty,
+            kind = tracing::field::Empty,
+        );
+        let result = provider.authenticate_token(token).instrument(span.clone()).await;
+        match &result {
+            Ok(user) => span.record("user_id", user.id.as_str()),
+            Err(e) => span.record("kind", e.kind()),
+        };
+        result
+    };
+    #[cfg(not(feature = "tracing"))]
+    let result = provider.authenticate_token(token).await;
+
+    #[cfg(feature = "metrics")]
+    crate::metrics::Metrics::global().observe_authentication(name, &result, started.elapsed());
+    #[cfg(not(feature = "metrics"))]
+    let _ = name;
+
+    result
+}
+
+/// Run a check generated by the attribute macros
+///
+/// Called by the code generated by the attribute macros, not meant to be
+/// used directly.
+#[doc(hidden)]
+pub fn check<P>(kind: AuditKind, user: &User<P>, requirement: &str, check: impl FnOnce() -> bool) -> bool {
+    #[cfg(feature = "tracing")]
+    let span = tracing::debug_span!(
+        "check",
+        kind = kind.name(),
+        requirement,
+        user_id = user.id.as_str(),
+        allowed = tracing::field::Empty,
+    )
+    .entered();
+    #[cfg(not(feature = "tracing"))]
+    let _ = (user, requirement);
+
+    let allowed = check();
+
+    #[cfg(feature = "tracing")]
+    span.record("allowed", allowed);
+
+    #[cfg(feature = "metrics")]
+    {
+        use crate::audit::Decision;
+
+        let decision = if allowed { Decision::Allow } else { Decision::Deny };
+        crate::metrics::Metrics::global().observe_check(kind.name(), decision);
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = kind;
+
+    allowed
+}
@@ -0,0 +1,96 @@
+//! Tenant-scoped role checks
+//!
+//! Users of multi-tenant applications often hold different roles in
+//! different organizations. Roles bound to a tenant with
+//! [`User::with_tenant_role`](crate::auth::User::with_tenant_role) only apply
+//! there, while the user's global roles apply in every tenant.
+//!
+//! A [`TenantSource`] tells a route where to find the tenant id of a
+//! request. The attribute macros accept one with the `tenant` option:
+//!
+//! ```rust,ignore
+//! // From the `<org_id>` path segment
+//! #[require_role("admin", tenant = "<org_id>")]
+//! #[get("/orgs/<org_id>/settings")]
+//! fn settings(org_id: &str) -> String { ... }
+//!
+//! // From a header
+//! #[require_role("viewer", tenant = header("X-Org-Id"))]
+//! #[get("/reports")]
+//! fn reports() -> String { ... }
+//!
+//! // From the subdomain, e.g. `acme` for `acme.example.com`
+//! #[require_permission("billing:read", tenant = subdomain("example.com"))]
+//! #[get("/billing")]
+//! fn billing() -> String { ... }
+//! ```
+
+use rocket::request::Request;
+
+/// Where the tenant id of a request comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TenantSource {
+    /// The dynamic path segment with the given name, e.g. `org_id` for a
+    /// route `/orgs/<org_id>`
+    Param(String),
+    /// The value of the given header
+    Header(String),
+    /// The subdomain below the given base domain, e.g. `acme` for
+    /// `acme.example.com` with base `example.com`
+    Subdomain(String),
+}
+
+impl TenantSource {
+    /// Read the tenant id from the named path segment
+    pub fn param(name: impl Into<String>) -> Self {
+        TenantSource::Param(name.into())
+    }
+
+    /// Read the tenant id from the given header
+    pub fn header(name: impl Into<String>) -> Self {
+        TenantSource::Header(name.into())
+    }
+
+    /// Read the tenant id from the subdomain below the given base domain
+    pub fn subdomain(base: impl Into<String>) -> Self {
+        TenantSource::Subdomain(base.into())
+    }
+
+    /// The tenant id of the request, if present
+    pub fn resolve(&self, request: &Request<'_>) -> Option<String> {
+        let tenant = match self {
+            TenantSource::Param(name) => {
+                // Find the position of `<name>` in the matched route, relative
+                // to its mount point
+                let route = request.route()?;
+                let placeholder = format!("<{}>", name);
+                let index = route
+                    .uri
+                    .unmounted_origin
+                    .path()
+                    .segments()
+                    .position(|segment| segment == placeholder)?;
+                request.param::<&str>(index)?.ok()?.to_string()
+            }
+            TenantSource::Header(name) => request.headers().get_one(name)?.to_string(),
+            TenantSource::Subdomain(base) => {
+                let host = request.host()?.domain().as_str().to_ascii_lowercase();
+                let base = base.trim_start_matches('.').to_ascii_lowercase();
+                let subdomain = host.strip_suffix(&base)?.strip_suffix('.')?;
+                subdomain.rsplit('.').next()?.to_string()
+            }
+        };
+
+        (!tenant.is_empty()).then_some(tenant)
+    }
+}
+
+impl std::fmt::Display for TenantSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TenantSource::Param(name) => write!(f, "path segment <{}>", name),
+            TenantSource::Header(name) => write!(f, "header {}", name),
+            TenantSource::Subdomain(base) => write!(f, "subdomain of {}", base),
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Unit tests for the audit log
+
+#[cfg(test)]
+mod tests {
+    use crate::audit::{AuditEvent, AuditKind, AuditSink, Decision, JsonLinesSink, MemorySink};
+    use crate::auth::{MaybeUser, User};
+    use crate::extract::{ExtractorChain, QueryExtractor};
+    use crate::fairing::RocketRoles;
+    use crate::tests::support::{self, TokenProvider};
+    use crate::{require_permission, require_role};
+    use rocket::http::{Header, Status};
+    use rocket::local::blocking::Client;
+    use rocket::{get, routes};
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    
+    #[require_role("auditor")]
+    #[get("/audits")]
+    fn audits(_also: MaybeUser) -> &'static str {
+        "audits"
+    }
+    
+    #[require_permission("reports:read", tenant = header("X-Org-Id"))]
+    #[get("/reports")]
+    fn reports() -> &'static str {
+        "reports"
+    }
+    
+    #[get("/home")]
+    fn home(user: MaybeUser) -> String {
+        user.as_ref().map_or("anonymous".to_string(), |user| user.username.clone())
+    }
+    
+    fn provider() -> TokenProvider {
+        TokenProvider::new()
+            .with_user("auditor", User::new("1", "alice").with_role("auditor").with_permission("reports:read"))
+            .with_user("guest", User::new("2", "bob"))
+    }
+    
+    fn client(sink: &MemorySink) -> Client {
+        support::client(rocket::build()
+            .attach(RocketRoles::new(provider(), HashMap::new()).with_audit_sink(sink.clone()))
+            .mount("/", routes![audits, reports, home]))
+    }
+    
+    // Test the events recorded for allowed and denied requests
+    #[test]
+    fn test_decisions() {
+        let sink = MemorySink::new();
+        let client = client(&sink);
+        let get = |uri: &'static str, token: &str| {
+            client.get(uri)
+                .header(Header::new("Authorization", format!("Bearer {}", token)))
+                .dispatch()
+                .status()
+        };
+        
+        // Authentication is recorded once, although two guards need the user
+        assert_eq!(get("/audits", "auditor"), Status::Ok);
+        let events = sink.events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].kind, AuditKind::Authentication);
+        assert_eq!(events[0].decision, Decision::Allow);
+        assert_eq!(events[0].user_id.as_deref(), Some("1"));
+        assert_eq!(events[1].kind, AuditKind::Role);
+        assert_eq!(events[1].decision, Decision::Allow);
+        assert_eq!(events[1].requirement.as_deref(), Some("'auditor'"));
+        assert_eq!(events[1].route.as_deref(), Some("audits"));
+        assert_eq!(events[1].method, "GET");
+        assert_eq!(events[1].uri, "/audits");
+        
+        sink.clear();
+        assert_eq!(get("/audits", "guest"), Status::Forbidden);
+        let events = sink.events();
+        assert_eq!(events[1].decision, Decision::Deny);
+        assert_eq!(events[1].username.as_deref(), Some("bob"));
+        assert_eq!(events[1].code, Some("missing_role"));
+        
+        sink.clear();
+        assert_eq!(get("/audits", "bogus"), Status::Unauthorized);
+        let events = sink.events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].decision, Decision::Deny);
+        assert_eq!(events[0].user_id, None);
+        assert_eq!(events[0].code, Some("invalid_token"));
+        
+        // A request without a credential is not a denial by itself
+        sink.clear();
+        assert_eq!(client.get("/home").dispatch().status(), Status::Ok);
+        let events = sink.events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].decision, Decision::Anonymous);
+        assert_eq!(events[0].user_id, None);
+        assert_eq!(events[0].code, None);
+        
+        sink.clear();
+        assert_eq!(client.get("/audits").dispatch().status(), Status::Unauthorized);
+        assert_eq!(sink.events()[0].decision, Decision::Anonymous);
+        
+        sink.clear();
+        let response = client.get("/reports")
+            .header(Header::new("Authorization", "Bearer auditor"))
+            .header(Header::new("X-Org-Id", "acme"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let events = sink.events();
+        assert_eq!(events[1].kind, AuditKind::Permission);
+        assert_eq!(events[1].requirement.as_deref(), Some("'reports:read' in tenant 'acme'"));
+        
+        sink.clear();
+        assert_eq!(get("/reports", "auditor"), Status::BadRequest);
+        assert_eq!(sink.events()[1].code, Some("missing_tenant"));
+    }
+    
+    // Test that credentials in the query are not recorded
+    #[test]
+    fn test_query_redaction() {
+        let sink = MemorySink::new();
+        let extractors = ExtractorChain::new().with(QueryExtractor::new("access_token"));
+        let client = support::client(rocket::build()
+            .attach(
+                RocketRoles::new(provider(), HashMap::new())
+                    .with_extractors(extractors)
+                    .with_audit_sink(sink.clone()),
+            )
+            .mount("/", routes![audits]));
+        
+        assert_eq!(client.get("/audits?access_token=auditor").dispatch().status(), Status::Ok);
+        let events = sink.events();
+        assert_eq!(events.len(), 2);
+        for event in events {
+            assert_eq!(event.uri, "/audits");
+            let json = serde_json::to_string(&event).unwrap();
+            assert!(!json.contains("access_token"));
+        }
+    }
+    
+    // Test that the JSON-lines sink writes one event per line and rotates
+    #[test]
+    fn test_json_lines_rotation() {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let dir = std::env::temp_dir().join(format!("rocket_roles_audit_{}_{}", std::process::id(), nanos));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("audit.jsonl");
+        
+        let client = support::client(rocket::build().mount("/", routes![audits]));
+        let request = client.get("/audits?page=2");
+        let event = AuditEvent::new(request.inner(), AuditKind::Authentication, Some(&User::new("1", "alice")), None);
+        let line_len = rocket::serde::json::to_string(&event).unwrap().len() as u64 + 1;
+        
+        let sink = JsonLinesSink::new(&path).unwrap()
+            .with_max_bytes(line_len * 2)
+            .with_max_files(2);
+        for _ in 0..7 {
+            sink.record(&event);
+        }
+        sink.flush();
+        
+        let lines = |path: &std::path::Path| std::fs::read_to_string(path).unwrap().lines().count();
+        assert_eq!(lines(&path), 1);
+        assert_eq!(lines(&dir.join("audit.jsonl.1")), 2);
+        assert_eq!(lines(&dir.join("audit.jsonl.2")), 2);
+        assert!(!dir.join("audit.jsonl.3").exists());
+        
+        let json: Value = serde_json::from_str(std::fs::read_to_string(&path).unwrap().trim()).unwrap();
+        assert_eq!(json["kind"], "authentication");
+        assert_eq!(json["decision"], "allow");
+        assert_eq!(json["user_id"], "1");
+        assert_eq!(json["uri"], "/audits");
+        assert!(json["timestamp"].as_u64().unwrap() > 0);
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -2,9 +2,20 @@
 
 #[cfg(test)]
 mod tests {
-    use crate::auth::{User, Role, AuthProvider, AuthError, register_auth_provider, register_roles};
+    use crate::auth::{
+        User, Role, AuthProvider, AuthError, AlreadyRegistered, register_auth_provider,
+        register_auth_provider_with_extractors, register_roles, resolve_role_hierarchy,
+    };
+    use crate::extract::{ExtractorChain, QueryExtractor};
+    use crate::auth::MaybeUser;
+    use crate::fairing::RocketRoles;
+    use crate::rejection::AuthRejection;
     use async_trait::async_trait;
+    use rocket::http::{Header, Status};
+    use rocket::local::blocking::Client;
     use std::collections::{HashMap, HashSet};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
     
     // Test user creation and permission checking
     #[test]
@@ -18,6 +29,7 @@ mod tests {
                 "create_user".to_string(),
                 "delete_user".to_string(),
             ]),
+            parents: Vec::new(),
         };
         
         let user_role = Role {
@@ -26,6 +38,7 @@ mod tests {
                 "view_profile".to_string(),
                 "edit_profile".to_string(),
             ]),
+            parents: Vec::new(),
         };
         
         roles.insert(admin_role.name.clone(), admin_role);
@@ -63,6 +76,52 @@ mod tests {
         assert!(!special.has_permission("create_user"));
     }
     
+    // Test that roles inherit permissions from their parents, transitively
+    #[test]
+    fn test_role_hierarchy() {
+        let mut roles = HashMap::new();
+        
+        let user_role = Role::new("user")
+            .with_permissions(vec!["view_profile", "edit_profile"]);
+        let moderator_role = Role::new("moderator")
+            .with_parent("user")
+            .with_permission("edit_post");
+        let admin_role = Role::new("admin")
+            .with_parent("moderator")
+            .with_permission("delete_user");
+        
+        roles.insert(user_role.name.clone(), user_role);
+        roles.insert(moderator_role.name.clone(), moderator_role);
+        roles.insert(admin_role.name.clone(), admin_role);
+        
+        let resolved = resolve_role_hierarchy(&roles);
+        
+        let admin = &resolved["admin"];
+        assert!(admin.permissions.contains("delete_user"));
+        assert!(admin.permissions.contains("edit_post"));
+        assert!(admin.permissions.contains("view_profile"));
+        
+        let moderator = &resolved["moderator"];
+        assert!(moderator.permissions.contains("edit_post"));
+        assert!(moderator.permissions.contains("edit_profile"));
+        assert!(!moderator.permissions.contains("delete_user"));
+        
+        assert_eq!(resolved["user"].permissions.len(), 2);
+    }
+    
+    // Test that a cycle in the hierarchy does not loop forever
+    #[test]
+    fn test_role_hierarchy_cycle() {
+        let mut roles = HashMap::new();
+        roles.insert("a".to_string(), Role::new("a").with_parent("b").with_permission("perm_a"));
+        roles.insert("b".to_string(), Role::new("b").with_parent("a").with_permission("perm_b"));
+        
+        let resolved = resolve_role_hierarchy(&roles);
+        
+        assert!(resolved["a"].permissions.contains("perm_b"));
+        assert!(resolved["b"].permissions.contains("perm_a"));
+    }
+    
     // Mock auth provider for testing
     struct MockAuthProvider;
     
@@ -79,8 +138,9 @@ mod tests {
     // Test token authentication
     #[tokio::test]
     async fn test_token_authentication() {
+        // Other tests may have registered the same provider first
         let provider = MockAuthProvider;
-        register_auth_provider(provider);
+        let _ = register_auth_provider(provider);
         
         // Valid token
         let user = crate::auth::get_auth_provider()
@@ -104,4 +164,127 @@ mod tests {
             _ => panic!("Expected InvalidToken error"),
         }
     }
+    
+    // Test the status mapping and source of auth errors
+    #[test]
+    fn test_error_status() {
+        use rocket::http::Status;
+        use std::error::Error;
+        use std::time::Duration;
+        
+        assert_eq!(AuthError::Expired.status(), Status::Unauthorized);
+        assert_eq!(AuthError::Revoked.code(), "token_revoked");
+        assert_eq!(AuthError::AccountLocked.status(), Status::Forbidden);
+        assert_eq!(AuthError::RateLimited { retry_after: Some(Duration::from_secs(5)) }.status(), Status::TooManyRequests);
+        assert_eq!(AuthError::DatabaseError("down".to_string()).status(), Status::ServiceUnavailable);
+        assert_eq!(AuthError::UserNotFound.code(), "invalid_token");
+        assert!(AuthError::AccountDisabled.is_client_error());
+        assert_eq!(AuthError::Other("bug".to_string()).status(), Status::Unauthorized);
+        assert_eq!(AuthError::Other("bug".to_string()).code(), "authentication_failed");
+        
+        let io = std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "connection refused");
+        let error = AuthError::unavailable("User store unreachable", io);
+        assert_eq!(error.status(), Status::ServiceUnavailable);
+        assert_eq!(error.to_string(), "Auth provider unavailable: User store unreachable");
+        assert_eq!(error.source().unwrap().to_string(), "connection refused");
+        assert!(error.clone().source().is_some());
+        assert!(AuthError::Expired.source().is_none());
+    }
+    
+    struct CountingProvider {
+        calls: Arc<AtomicUsize>,
+    }
+    
+    #[async_trait]
+    impl AuthProvider for CountingProvider {
+        async fn authenticate_token(&self, token: &str) -> Result<User, AuthError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match token {
+                "alice" => Ok(User::new("1", "alice")),
+                _ => Err(AuthError::InvalidToken("Invalid token".to_string())),
+            }
+        }
+    }
+    
+    #[rocket::get("/")]
+    fn both(first: Result<User, AuthRejection>, second: MaybeUser) -> String {
+        format!("{} {}", first.is_ok(), second.is_some())
+    }
+    
+    // Test that the outcome is computed once per request, including failures
+    #[test]
+    fn test_request_local_caching() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let provider = CountingProvider { calls: calls.clone() };
+        let rocket = rocket::build()
+            .attach(RocketRoles::new(provider, HashMap::new()))
+            .mount("/", rocket::routes![both]);
+        let client = Client::untracked(rocket).expect("valid rocket instance");
+        let get = |token: &str| {
+            client.get("/")
+                .header(Header::new("Authorization", format!("Bearer {}", token)))
+                .dispatch()
+        };
+        
+        let response = get("alice");
+        assert_eq!(response.into_string().unwrap(), "true true");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        
+        assert_eq!(get("bogus").status(), Status::Unauthorized);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+    
+    // Test that a second registration is refused and changes nothing
+    #[test]
+    fn test_double_registration() {
+        let _ = register_auth_provider(MockAuthProvider);
+        assert_eq!(register_auth_provider(MockAuthProvider), Err(AlreadyRegistered));
+        
+        let extractors = ExtractorChain::new().with(QueryExtractor::new("access_token"));
+        assert_eq!(
+            register_auth_provider_with_extractors(MockAuthProvider, extractors),
+            Err(AlreadyRegistered)
+        );
+        let rocket = rocket::build().mount("/", rocket::routes![me]);
+        let client = Client::untracked(rocket).expect("valid rocket instance");
+        assert_eq!(client.get("/me?access_token=valid_token").dispatch().status(), Status::Unauthorized);
+        let response = client.get("/me")
+            .header(Header::new("Authorization", "Bearer valid_token"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+    
+    #[rocket::get("/me")]
+    fn me(user: User) -> String {
+        user.username
+    }
+    
+    // Test that rejections on the global provider path carry challenges when
+    // the catchers are registered, without the fairing
+    #[test]
+    fn test_global_provider_challenges() {
+        let _ = register_auth_provider(MockAuthProvider);
+        let rocket = rocket::build()
+            .register("/", crate::rejection::json_catchers())
+            .mount("/", rocket::routes![me]);
+        let client = Client::untracked(rocket).expect("valid rocket instance");
+        
+        let response = client.get("/me").dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+        assert_eq!(response.headers().get_one("WWW-Authenticate"), Some("Bearer"));
+        
+        let response = client.get("/me")
+            .header(Header::new("Authorization", "Bearer invalid_token"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+        assert_eq!(
+            response.headers().get_one("WWW-Authenticate"),
+            Some(r#"Bearer error="invalid_token", error_description="Invalid token""#)
+        );
+        
+        let response = client.get("/me")
+            .header(Header::new("Authorization", "Bearer valid_token"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
 }
@@ -0,0 +1,143 @@
+//! Unit tests for the caching auth provider
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{AuthError, AuthProvider, User};
+    use crate::cache::CachedAuthProvider;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Notify;
+    
+    // Provider that counts how often it is called
+    #[derive(Default)]
+    struct CountingProvider {
+        calls: AtomicUsize,
+    }
+    
+    #[async_trait]
+    impl AuthProvider for CountingProvider {
+        async fn authenticate_token(&self, token: &str) -> Result<User, AuthError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match token {
+                "alice_token" | "alice_other_token" => Ok(User::new("1", "alice")),
+                "bob_token" => Ok(User::new("2", "bob")),
+                "broken" => Err(AuthError::DatabaseError("connection refused".to_string())),
+                _ => Err(AuthError::InvalidToken("Invalid token".to_string())),
+            }
+        }
+    }
+    
+    impl CachedAuthProvider<CountingProvider> {
+        fn calls(&self) -> usize {
+            self.inner().calls.load(Ordering::SeqCst)
+        }
+    }
+    
+    // Test that successful results are cached until they expire
+    #[tokio::test(start_paused = true)]
+    async fn test_positive_caching() {
+        let provider = CachedAuthProvider::new(CountingProvider::default())
+            .with_ttl(Duration::from_millis(100));
+        
+        assert_eq!(provider.authenticate_token("alice_token").await.unwrap().username, "alice");
+        assert_eq!(provider.authenticate_token("alice_token").await.unwrap().username, "alice");
+        assert_eq!(provider.calls(), 1);
+        
+        tokio::time::advance(Duration::from_millis(150)).await;
+        provider.authenticate_token("alice_token").await.unwrap();
+        assert_eq!(provider.calls(), 2);
+    }
+    
+    // Test that failures are only cached when enabled and definitive
+    #[tokio::test]
+    async fn test_negative_caching() {
+        let provider = CachedAuthProvider::new(CountingProvider::default());
+        assert!(provider.authenticate_token("bad").await.is_err());
+        assert!(provider.authenticate_token("bad").await.is_err());
+        assert_eq!(provider.calls(), 2);
+        
+        let provider = CachedAuthProvider::new(CountingProvider::default())
+            .with_negative_ttl(Duration::from_secs(10));
+        assert!(matches!(provider.authenticate_token("bad").await, Err(AuthError::InvalidToken(_))));
+        assert!(matches!(provider.authenticate_token("bad").await, Err(AuthError::InvalidToken(_))));
+        assert_eq!(provider.calls(), 1);
+        
+        // Backend failures are never cached
+        assert!(provider.authenticate_token("broken").await.is_err());
+        assert!(provider.authenticate_token("broken").await.is_err());
+        assert_eq!(provider.calls(), 3);
+    }
+    
+    // Test invalidation by token and by user
+    #[tokio::test]
+    async fn test_invalidation() {
+        let provider = Arc::new(CachedAuthProvider::new(CountingProvider::default()));
+        
+        for token in ["alice_token", "alice_other_token", "bob_token"] {
+            provider.authenticate_token(token).await.unwrap();
+        }
+        assert_eq!(provider.len(), 3);
+        
+        provider.invalidate_token("bob_token");
+        assert_eq!(provider.len(), 2);
+        
+        provider.invalidate_user("1");
+        assert!(provider.is_empty());
+        
+        provider.authenticate_token("alice_token").await.unwrap();
+        assert_eq!(provider.calls(), 4);
+    }
+    
+    // Test that the cache never grows past its maximum size
+    #[tokio::test]
+    async fn test_max_entries() {
+        let provider = CachedAuthProvider::new(CountingProvider::default())
+            .with_max_entries(2);
+        
+        provider.authenticate_token("alice_token").await.unwrap();
+        provider.authenticate_token("alice_other_token").await.unwrap();
+        provider.authenticate_token("bob_token").await.unwrap();
+        assert_eq!(provider.len(), 2);
+        
+        // The entry closest to expiring was evicted
+        provider.authenticate_token("bob_token").await.unwrap();
+        assert_eq!(provider.calls(), 3);
+        provider.authenticate_token("alice_token").await.unwrap();
+        assert_eq!(provider.calls(), 4);
+    }
+    
+    // Provider that waits to be released before answering
+    #[derive(Default)]
+    struct GatedProvider {
+        entered: Notify,
+        release: Notify,
+    }
+    
+    #[async_trait]
+    impl AuthProvider for GatedProvider {
+        async fn authenticate_token(&self, _token: &str) -> Result<User, AuthError> {
+            self.entered.notify_one();
+            self.release.notified().await;
+            Ok(User::new("1", "alice"))
+        }
+    }
+    
+    // Test that a result fetched while the user was invalidated is not cached
+    #[tokio::test]
+    async fn test_invalidation_during_fetch() {
+        let provider = Arc::new(CachedAuthProvider::new(GatedProvider::default()));
+        
+        let task = tokio::spawn({
+            let provider = provider.clone();
+            async move { provider.authenticate_token("alice_token").await }
+        });
+        provider.inner().entered.notified().await;
+        provider.invalidate_user("1");
+        provider.inner().release.notify_one();
+        
+        assert_eq!(task.await.unwrap().unwrap().id, "1");
+        assert!(provider.is_empty());
+    }
+}
@@ -0,0 +1,138 @@
+//! Unit tests for the provider chain
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{AuthError, AuthProvider, User};
+    use crate::chain::{FallThrough, ProviderChain, TokenMatch};
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    
+    // Provider that knows a fixed set of tokens and counts its calls
+    #[derive(Default)]
+    struct FixedProvider {
+        tokens: HashMap<&'static str, Result<&'static str, AuthError>>,
+        calls: AtomicUsize,
+    }
+    
+    impl FixedProvider {
+        fn new<const N: usize>(tokens: [(&'static str, Result<&'static str, AuthError>); N]) -> Arc<Self> {
+            Arc::new(Self { tokens: HashMap::from(tokens), calls: AtomicUsize::new(0) })
+        }
+        
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+    
+    #[async_trait]
+    impl AuthProvider for FixedProvider {
+        async fn authenticate_token(&self, token: &str) -> Result<User, AuthError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match self.tokens.get(token) {
+                Some(Ok(name)) => Ok(User::new(*name, *name)),
+                Some(Err(e)) => Err(e.clone()),
+                None => Err(AuthError::InvalidToken("Unknown token".to_string())),
+            }
+        }
+    }
+    
+    // Test that providers are tried in order until one accepts the token
+    #[tokio::test]
+    async fn test_fall_through() {
+        let jwt = FixedProvider::new([("a.b.c", Ok("jwt"))]);
+        let legacy = FixedProvider::new([
+            ("opaque", Ok("legacy")),
+            ("old", Err(AuthError::Expired)),
+        ]);
+        let chain = ProviderChain::new()
+            .with_provider(jwt.clone())
+            .with_provider(legacy.clone());
+        assert_eq!(chain.len(), 2);
+        
+        assert_eq!(chain.authenticate_token("a.b.c").await.unwrap().username, "jwt");
+        assert_eq!(legacy.calls(), 0);
+        assert_eq!(chain.authenticate_token("opaque").await.unwrap().username, "legacy");
+        assert!(matches!(chain.authenticate_token("old").await, Err(AuthError::Expired)));
+        assert!(matches!(chain.authenticate_token("bogus").await, Err(AuthError::InvalidToken(_))));
+        assert_eq!(jwt.calls(), 4);
+    }
+    
+    // Test that a recognized but refused token is not passed on
+    #[tokio::test]
+    async fn test_definitive_errors() {
+        let first = FixedProvider::new([("revoked", Err(AuthError::Revoked))]);
+        let second = FixedProvider::new([("revoked", Ok("second"))]);
+        let chain = ProviderChain::new()
+            .with_provider(first)
+            .with_provider(second.clone());
+        
+        assert!(matches!(chain.authenticate_token("revoked").await, Err(AuthError::Revoked)));
+        assert_eq!(second.calls(), 0);
+        
+        let first = FixedProvider::new([("odd", Err(AuthError::Other("unexpected claims".to_string())))]);
+        let second = FixedProvider::new([("odd", Ok("second"))]);
+        let chain = ProviderChain::new()
+            .with_provider(first)
+            .with_provider(second.clone());
+        
+        assert!(matches!(chain.authenticate_token("odd").await, Err(AuthError::Other(_))));
+        assert_eq!(second.calls(), 0);
+    }
+    
+    // Test that the chain can stop at the first provider's error
+    #[tokio::test]
+    async fn test_never_fall_through() {
+        let first = FixedProvider::new([]);
+        let second = FixedProvider::new([("opaque", Ok("second"))]);
+        let chain = ProviderChain::new()
+            .with_provider(first)
+            .with_provider(second.clone())
+            .with_fall_through(FallThrough::Never);
+        
+        assert!(matches!(chain.authenticate_token("opaque").await, Err(AuthError::InvalidToken(_))));
+        assert_eq!(second.calls(), 0);
+    }
+    
+    // Test that unavailable providers are skipped but reported if nothing else succeeds
+    #[tokio::test]
+    async fn test_unavailable() {
+        let down = FixedProvider::new([
+            ("opaque", Err(AuthError::DatabaseError("connection refused".to_string()))),
+            ("bogus", Err(AuthError::DatabaseError("connection refused".to_string()))),
+        ]);
+        let backup = FixedProvider::new([("opaque", Ok("backup"))]);
+        let chain = ProviderChain::new()
+            .with_provider(down)
+            .with_provider(backup);
+        
+        assert_eq!(chain.authenticate_token("opaque").await.unwrap().username, "backup");
+        assert!(matches!(chain.authenticate_token("bogus").await, Err(AuthError::DatabaseError(_))));
+    }
+    
+    // Test routing by token prefix and format
+    #[tokio::test]
+    async fn test_routing() {
+        let jwt = FixedProvider::new([("eyJh.eyJz.sig", Ok("jwt"))]);
+        let keys = FixedProvider::new([("sk_live_1", Ok("key"))]);
+        let internal = FixedProvider::new([("svc-1", Ok("service"))]);
+        let chain = ProviderChain::new()
+            .with_provider_for(TokenMatch::jwt(), jwt.clone())
+            .with_provider_for(TokenMatch::prefix("sk_"), keys.clone())
+            .with_provider_for(TokenMatch::custom(|token| token.starts_with("svc-")), internal.clone());
+        
+        assert_eq!(chain.authenticate_token("eyJh.eyJz.sig").await.unwrap().username, "jwt");
+        assert_eq!(chain.authenticate_token("sk_live_1").await.unwrap().username, "key");
+        assert_eq!(chain.authenticate_token("svc-1").await.unwrap().username, "service");
+        assert_eq!((jwt.calls(), keys.calls(), internal.calls()), (1, 1, 1));
+        
+        let error = chain.authenticate_token("opaque").await.unwrap_err();
+        assert_eq!(error.to_string(), "Invalid token: No provider accepts this token");
+        assert_eq!((jwt.calls(), keys.calls(), internal.calls()), (1, 1, 1));
+        
+        assert!(TokenMatch::jwt().matches("a-b.c_d."));
+        assert!(!TokenMatch::jwt().matches("a.b"));
+        assert!(!TokenMatch::jwt().matches("a.b c.d"));
+    }
+}
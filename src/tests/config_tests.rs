@@ -0,0 +1,147 @@
+//! Unit tests for loading roles from configuration
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{AuthError, AuthProvider, User};
+    use crate::config::{build_roles, RoleSpec};
+    use crate::fairing::RocketRoles;
+    use async_trait::async_trait;
+    use rocket::figment::providers::{Format, Toml};
+    use rocket::local::blocking::Client;
+    use std::collections::HashMap;
+    
+    fn spec(permissions: &[&str], parents: &[&str]) -> RoleSpec {
+        RoleSpec {
+            permissions: permissions.iter().map(|p| p.to_string()).collect(),
+            parents: parents.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+    
+    // Test validation of parents and cycles
+    #[test]
+    fn test_build_roles_validation() {
+        let mut specs = HashMap::new();
+        specs.insert("user".to_string(), spec(&["view_profile"], &[]));
+        specs.insert("admin".to_string(), spec(&["delete_user"], &["user"]));
+        let roles = build_roles(specs, None).unwrap();
+        assert_eq!(roles["admin"].parents, vec!["user".to_string()]);
+        assert!(roles["user"].permissions.contains("view_profile"));
+        
+        let mut specs = HashMap::new();
+        specs.insert("admin".to_string(), spec(&[], &["missing"]));
+        let error = build_roles(specs, None).unwrap_err();
+        assert!(error.message.contains("unknown parent role 'missing'"));
+        
+        let mut specs = HashMap::new();
+        specs.insert("a".to_string(), spec(&[], &["b"]));
+        specs.insert("b".to_string(), spec(&[], &["a"]));
+        let error = build_roles(specs, None).unwrap_err();
+        assert!(error.message.contains("cycle"));
+    }
+    
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_roles_from_toml() {
+        use crate::config::roles_from_toml_str;
+        
+        let roles = roles_from_toml_str(r#"
+            [user]
+            permissions = ["view_profile"]
+            
+            [admin]
+            parents = ["user"]
+            permissions = ["delete_user"]
+        "#).unwrap();
+        assert_eq!(roles.len(), 2);
+        
+        let error = roles_from_toml_str("[user]\npermissions = [\"view_profile\"]\n\n[admin]\nparents = [\"nobody\"]\n").unwrap_err();
+        assert_eq!(error.line, Some(5));
+        
+        let error = roles_from_toml_str("[user]\npermisions = []\n").unwrap_err();
+        assert_eq!(error.line, Some(2));
+        
+        // The error points into `user`, not the earlier `user_admin` whose
+        // name starts with it
+        let error = roles_from_toml_str(r#"
+            [user_admin]
+            parents = ["user"]
+            permissions = ["guest", "guest:read"]
+            
+            [user]
+            parents = ["guest"]
+        "#).unwrap_err();
+        assert_eq!(error.message, "role 'user' has unknown parent role 'guest'");
+        assert_eq!(error.line, Some(7));
+    }
+    
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_roles_from_json() {
+        use crate::registry::RoleRegistry;
+        
+        let registry = RoleRegistry::from_json(r#"{
+            "user": { "permissions": ["view_profile"] },
+            "admin": { "parents": ["user"], "permissions": ["delete_user"] }
+        }"#).unwrap();
+        assert!(registry.roles()["admin"].permissions.contains("view_profile"));
+        
+        let error = RoleRegistry::from_json("{\n  \"user\": {\n    \"permissions\": 3\n  }\n}").unwrap_err();
+        assert_eq!(error.line, Some(3));
+        
+        let error = RoleRegistry::from_json(r#"{
+            "poweruser": { "permissions": ["ghost"] },
+            "user": { "parents": ["ghost"] }
+        }"#).unwrap_err();
+        assert_eq!(error.line, Some(3));
+    }
+    
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_roles_from_yaml() {
+        use crate::registry::RoleRegistry;
+        
+        let registry = RoleRegistry::from_yaml("user:\n  permissions: [view_profile]\nadmin:\n  parents: [user]\n").unwrap();
+        assert!(registry.roles()["admin"].permissions.contains("view_profile"));
+        
+        let error = RoleRegistry::from_yaml("user:\n  permissions: [view_profile]\nadmin:\n  parents: [nobody]\n").unwrap_err();
+        assert_eq!(error.line, Some(4));
+        
+        let error = RoleRegistry::from_yaml("users:\n  permissions: [nobody]\nuser:\n  parents: [nobody]\n").unwrap_err();
+        assert_eq!(error.line, Some(4));
+    }
+    
+    struct NoUsers;
+    
+    #[async_trait]
+    impl AuthProvider for NoUsers {
+        async fn authenticate_token(&self, _token: &str) -> Result<User, AuthError> {
+            Err(AuthError::UserNotFound)
+        }
+    }
+    
+    // Test reading roles from the Rocket configuration
+    #[test]
+    fn test_roles_from_rocket_config() {
+        let figment = rocket::Config::figment().merge(Toml::string(r#"
+            [roles.user]
+            permissions = ["view_profile"]
+            
+            [roles.admin]
+            parents = ["user"]
+        "#));
+        let client = Client::untracked(rocket::custom(figment).attach(RocketRoles::from_config(NoUsers)))
+            .expect("valid rocket instance");
+        
+        let fairing = client.rocket().state::<RocketRoles>().unwrap();
+        assert!(fairing.registry().roles()["admin"].permissions.contains("view_profile"));
+        
+        let figment = rocket::Config::figment().merge(Toml::string(r#"
+            [roles.admin]
+            parents = ["nobody"]
+        "#));
+        match Client::untracked(rocket::custom(figment).attach(RocketRoles::from_config(NoUsers))) {
+            Ok(_) => panic!("Expected ignition to fail"),
+            Err(e) => assert!(matches!(e.kind(), rocket::error::ErrorKind::FailedFairings(_))),
+        }
+    }
+}
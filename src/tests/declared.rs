@@ -0,0 +1,19 @@
+//! Roles and permissions used by the routes in the tests
+//!
+//! With the `check-names` feature, the names the routes require are checked
+//! against these, through the macro re-exported at the crate root.
+
+use crate::define_roles;
+
+define_roles! {
+    "support" => ["edit_post", "posts:*"],
+    "trainee" => ["banned"],
+    "admin": "support" => ["manage_users", "billing:*"],
+    "viewer" => [],
+    "operator" => [],
+    "staff" => [],
+    "customer" => [],
+    "auditor" => ["reports:read"],
+    // Covered by the wildcard grants above, but named by routes
+    permissions => ["posts:publish", "billing:read"]
+}
@@ -0,0 +1,76 @@
+//! Unit tests for token extraction
+
+#[cfg(test)]
+mod tests {
+    use crate::extract::{
+        BearerExtractor, CookieExtractor, ExtractorChain, HeaderExtractor, QueryExtractor, TokenExtractor,
+    };
+    use rocket::http::{Cookie, Header};
+    use rocket::local::blocking::Client;
+    
+    fn client() -> Client {
+        Client::untracked(rocket::build()).expect("valid rocket instance")
+    }
+    
+    // Test the default Bearer header extractor
+    #[test]
+    fn test_bearer_extractor() {
+        let client = client();
+        
+        let request = client.get("/").header(Header::new("Authorization", "Bearer abc"));
+        assert_eq!(BearerExtractor.extract(request.inner()), Ok(Some("abc".to_string())));
+        
+        let request = client.get("/").header(Header::new("Authorization", "Basic abc"));
+        assert!(BearerExtractor.extract(request.inner()).is_err());
+        
+        let request = client.get("/");
+        assert_eq!(BearerExtractor.extract(request.inner()), Ok(None));
+    }
+    
+    // Test custom header, cookie and query parameter extractors
+    #[test]
+    fn test_other_extractors() {
+        let client = client();
+        
+        let request = client.get("/").header(Header::new("X-Api-Key", "key123"));
+        let extractor = HeaderExtractor::new("X-Api-Key");
+        assert_eq!(extractor.extract(request.inner()), Ok(Some("key123".to_string())));
+        
+        let request = client.get("/").header(Header::new("X-Api-Key", "Key key123"));
+        let extractor = HeaderExtractor::new("X-Api-Key").with_prefix("Key ");
+        assert_eq!(extractor.extract(request.inner()), Ok(Some("key123".to_string())));
+        
+        let request = client.get("/").cookie(Cookie::new("session", "cookie123"));
+        let extractor = CookieExtractor::new("session");
+        assert_eq!(extractor.extract(request.inner()), Ok(Some("cookie123".to_string())));
+        
+        let request = client.get("/socket?access_token=query123");
+        let extractor = QueryExtractor::new("access_token");
+        assert_eq!(extractor.extract(request.inner()), Ok(Some("query123".to_string())));
+    }
+    
+    // Test that a chain tries extractors in order
+    #[test]
+    fn test_extractor_chain() {
+        let client = client();
+        let chain = ExtractorChain::new()
+            .with(BearerExtractor)
+            .with(HeaderExtractor::new("X-Api-Key"))
+            .with(QueryExtractor::new("access_token"));
+        
+        let request = client.get("/?access_token=query123")
+            .header(Header::new("X-Api-Key", "key123"));
+        assert_eq!(chain.extract(request.inner()), Ok(Some("key123".to_string())));
+        
+        let request = client.get("/?access_token=query123");
+        assert_eq!(chain.extract(request.inner()), Ok(Some("query123".to_string())));
+        
+        let request = client.get("/");
+        assert_eq!(chain.extract(request.inner()), Ok(None));
+        
+        // A malformed credential stops the chain
+        let request = client.get("/?access_token=query123")
+            .header(Header::new("Authorization", "Basic abc"));
+        assert!(chain.extract(request.inner()).is_err());
+    }
+}
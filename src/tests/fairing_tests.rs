@@ -0,0 +1,112 @@
+//! Unit tests for the RocketRoles fairing
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{AuthError, AuthProvider, Role, User};
+    use crate::extract::{ExtractorChain, HeaderExtractor};
+    use crate::fairing::RocketRoles;
+    use async_trait::async_trait;
+    use rocket::http::{Header, Status};
+    use rocket::error::ErrorKind;
+    use rocket::local::blocking::Client;
+    use rocket::{get, routes};
+    use std::collections::HashMap;
+    
+    // Provider that accepts a single fixed token
+    struct FixedTokenProvider {
+        token: &'static str,
+        username: &'static str,
+    }
+    
+    #[async_trait]
+    impl AuthProvider for FixedTokenProvider {
+        async fn authenticate_token(&self, token: &str) -> Result<User, AuthError> {
+            if token == self.token {
+                Ok(User::new("1", self.username).with_role("editor"))
+            } else {
+                Err(AuthError::InvalidToken("Invalid token".to_string()))
+            }
+        }
+    }
+    
+    #[get("/me")]
+    fn me(user: User) -> String {
+        format!("{}:{}", user.username, user.has_permission("edit_post"))
+    }
+    
+    fn client(fairing: RocketRoles) -> Client {
+        Client::untracked(rocket::build().attach(fairing).mount("/", routes![me]))
+            .expect("valid rocket instance")
+    }
+    
+    // Test that two instances in one process keep separate configurations
+    #[test]
+    fn test_independent_instances() {
+        let mut editor_roles = HashMap::new();
+        editor_roles.insert("editor".to_string(), Role::new("editor").with_permission("edit_post"));
+        
+        let mut viewer_roles = HashMap::new();
+        viewer_roles.insert("editor".to_string(), Role::new("editor").with_permission("view_post"));
+        
+        let first = client(RocketRoles::new(
+            FixedTokenProvider { token: "first_token", username: "first" },
+            editor_roles,
+        ));
+        let second = client(RocketRoles::new(
+            FixedTokenProvider { token: "second_token", username: "second" },
+            viewer_roles,
+        ));
+        
+        let response = first.get("/me")
+            .header(Header::new("Authorization", "Bearer first_token"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.into_string().unwrap(), "first:true");
+        
+        let response = second.get("/me")
+            .header(Header::new("Authorization", "Bearer second_token"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.into_string().unwrap(), "second:false");
+        
+        let response = second.get("/me")
+            .header(Header::new("Authorization", "Bearer first_token"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+        
+        let response = first.get("/me").dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+    
+    // Test that the fairing's extractor chain is used by the guard
+    #[test]
+    fn test_fairing_extractors() {
+        let client = client(
+            RocketRoles::new(
+                FixedTokenProvider { token: "api_key", username: "webhook" },
+                HashMap::new(),
+            )
+            .with_extractors(ExtractorChain::new().with(HeaderExtractor::new("X-Api-Key"))),
+        );
+        
+        let response = client.get("/me")
+            .header(Header::new("X-Api-Key", "api_key"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        
+        let response = client.get("/me")
+            .header(Header::new("Authorization", "Bearer api_key"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+    
+    // Test that attaching the fairing twice fails ignition instead of panicking
+    #[test]
+    fn test_attached_twice() {
+        let fairing = || RocketRoles::new(FixedTokenProvider { token: "token", username: "alice" }, HashMap::new());
+        let rocket = rocket::build().attach(fairing()).attach(fairing()).mount("/", routes![me]);
+        
+        let error = Client::untracked(rocket).expect_err("ignition fails");
+        assert!(matches!(error.kind(), ErrorKind::FailedFairings(failures) if failures[0].name == "Rocket Roles"));
+    }
+}
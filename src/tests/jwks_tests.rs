@@ -0,0 +1,197 @@
+//! Unit tests for JWKS key resolution
+
+#[cfg(all(test, feature = "jwt"))]
+mod tests {
+    use crate::auth::{AuthError, AuthProvider};
+    use crate::jwks::{JwksFetcher, JwksKeySource};
+    use crate::jwt::JwtAuthProvider;
+    use async_trait::async_trait;
+    use jsonwebtoken::jwk::JwkSet;
+    use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    use tokio::time;
+    
+    const JWKS: &str = include_str!("keys/jwks.json");
+    const RSA_PRIVATE: &[u8] = include_bytes!("keys/rsa_private.pem");
+    const EC_PRIVATE: &[u8] = include_bytes!("keys/ec_private.pem");
+    
+    fn token(algorithm: Algorithm, kid: &str) -> String {
+        let key = match algorithm {
+            Algorithm::RS256 => EncodingKey::from_rsa_pem(RSA_PRIVATE).unwrap(),
+            _ => EncodingKey::from_ec_pem(EC_PRIVATE).unwrap(),
+        };
+        let mut header = Header::new(algorithm);
+        header.kid = Some(kid.to_string());
+        let exp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() + 300;
+        encode(&header, &json!({ "sub": "42", "exp": exp }), &key).unwrap()
+    }
+    
+    fn provider(keys: JwksKeySource) -> JwtAuthProvider {
+        JwtAuthProvider::from_key_source(keys, &[Algorithm::RS256, Algorithm::ES256])
+    }
+    
+    // Fetcher that serves a configurable key set and counts fetches
+    struct CountingFetcher {
+        set: Arc<std::sync::Mutex<JwkSet>>,
+        fetches: Arc<AtomicUsize>,
+    }
+    
+    #[async_trait]
+    impl JwksFetcher for CountingFetcher {
+        async fn fetch(&self) -> Result<JwkSet, AuthError> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            Ok(self.set.lock().unwrap().clone())
+        }
+    }
+    
+    // Fetcher whose identity provider is down
+    struct FailingFetcher {
+        fetches: Arc<AtomicUsize>,
+    }
+    
+    #[async_trait]
+    impl JwksFetcher for FailingFetcher {
+        async fn fetch(&self) -> Result<JwkSet, AuthError> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            Err(AuthError::unavailable("JWKS endpoint unreachable", std::io::Error::other("connection refused")))
+        }
+    }
+    
+    // Test resolving keys by kid from a fixed key set
+    #[tokio::test]
+    async fn test_static_jwks() {
+        let provider = provider(JwksKeySource::from_jwks_str(JWKS).unwrap());
+        
+        assert!(provider.authenticate_token(&token(Algorithm::RS256, "rsa-key")).await.is_ok());
+        assert!(provider.authenticate_token(&token(Algorithm::ES256, "ec-key")).await.is_ok());
+        assert!(provider.authenticate_token(&token(Algorithm::RS256, "unknown")).await.is_err());
+        
+        // The key for a kid must match the token's signature
+        assert!(provider.authenticate_token(&token(Algorithm::ES256, "rsa-key")).await.is_err());
+    }
+    
+    // Test refreshing on unknown kid, with rate limiting
+    #[tokio::test(start_paused = true)]
+    async fn test_refresh_on_unknown_kid() {
+        let full: JwkSet = serde_json::from_str(JWKS).unwrap();
+        let mut rsa_only = full.clone();
+        rsa_only.keys.retain(|jwk| jwk.common.key_id.as_deref() == Some("rsa-key"));
+        
+        let set = Arc::new(std::sync::Mutex::new(rsa_only));
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let fetcher = CountingFetcher { set: set.clone(), fetches: fetches.clone() };
+        let provider = provider(JwksKeySource::new(fetcher).with_refresh_interval(Duration::from_millis(200)));
+        
+        // First token loads the set, later ones use the cache
+        assert!(provider.authenticate_token(&token(Algorithm::RS256, "rsa-key")).await.is_ok());
+        assert!(provider.authenticate_token(&token(Algorithm::RS256, "rsa-key")).await.is_ok());
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        
+        // The identity provider rotates in a new key
+        *set.lock().unwrap() = full;
+        
+        // Too soon after the last refresh, so the unknown kid is rejected
+        assert!(provider.authenticate_token(&token(Algorithm::ES256, "ec-key")).await.is_err());
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        
+        time::advance(Duration::from_millis(250)).await;
+        assert!(provider.authenticate_token(&token(Algorithm::ES256, "ec-key")).await.is_ok());
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+    
+    // Test that a failed fetch keeps reporting the provider as unavailable
+    #[tokio::test(start_paused = true)]
+    async fn test_failed_fetch() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let fetcher = FailingFetcher { fetches: fetches.clone() };
+        let provider = provider(JwksKeySource::new(fetcher).with_refresh_interval(Duration::from_secs(60)));
+        
+        let result = provider.authenticate_token(&token(Algorithm::RS256, "rsa-key")).await;
+        assert!(matches!(result, Err(AuthError::ProviderUnavailable { .. })));
+        
+        // Within the refresh interval the fetch is not retried, but the
+        // token is still not reported as invalid
+        let result = provider.authenticate_token(&token(Algorithm::RS256, "rsa-key")).await;
+        assert!(matches!(result, Err(AuthError::ProviderUnavailable { .. })));
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        
+        time::advance(Duration::from_secs(61)).await;
+        let result = provider.authenticate_token(&token(Algorithm::RS256, "rsa-key")).await;
+        assert!(matches!(result, Err(AuthError::ProviderUnavailable { .. })));
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+    
+    // Test that unusable keys are skipped instead of failing the whole set
+    #[tokio::test]
+    async fn test_unusable_keys_skipped() {
+        let mut set: serde_json::Value = serde_json::from_str(JWKS).unwrap();
+        let keys = set["keys"].as_array_mut().unwrap();
+        keys.push(json!({ "kty": "EC", "kid": "broken", "crv": "P-256", "x": "!!", "y": "!!" }));
+        keys.push(json!({ "kty": "OKP-unknown", "kid": "unknown-type" }));
+        let mut encryption = keys[0].clone();
+        encryption["kid"] = json!("enc-key");
+        encryption["use"] = json!("enc");
+        keys.push(encryption);
+        
+        let provider = provider(JwksKeySource::from_jwks_str(&set.to_string()).unwrap());
+        assert!(provider.authenticate_token(&token(Algorithm::RS256, "rsa-key")).await.is_ok());
+        assert!(provider.authenticate_token(&token(Algorithm::ES256, "ec-key")).await.is_ok());
+        assert!(provider.authenticate_token(&token(Algorithm::RS256, "enc-key")).await.is_err());
+        
+        // A set without any usable key is an error
+        let only_bad = json!({ "keys": [{ "kty": "OKP-unknown", "kid": "unknown-type" }] });
+        assert!(matches!(
+            JwksKeySource::from_jwks_str(&only_bad.to_string()),
+            Err(AuthError::ProviderUnavailable { .. })
+        ));
+    }
+    
+    // Test loading keys from a local JWKS file
+    #[tokio::test]
+    async fn test_jwks_file() {
+        let path = std::env::temp_dir().join(format!("rocket_roles_jwks_{}.json", std::process::id()));
+        std::fs::write(&path, JWKS).unwrap();
+        
+        let provider = provider(JwksKeySource::from_file(&path));
+        let result = provider.authenticate_token(&token(Algorithm::RS256, "rsa-key")).await;
+        std::fs::remove_file(&path).unwrap();
+        
+        assert!(result.is_ok());
+    }
+    
+    // Test fetching keys from a stub HTTP server
+    #[cfg(feature = "jwks")]
+    #[tokio::test]
+    async fn test_jwks_http() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+        
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/jwks.json", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        
+        let served = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                served.fetch_add(1, Ordering::SeqCst);
+                let mut buffer = [0; 1024];
+                let _ = stream.read(&mut buffer).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    JWKS.len(),
+                    JWKS
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        
+        let provider = provider(JwksKeySource::from_url(url));
+        assert!(provider.authenticate_token(&token(Algorithm::RS256, "rsa-key")).await.is_ok());
+        assert!(provider.authenticate_token(&token(Algorithm::ES256, "ec-key")).await.is_ok());
+        assert!(provider.authenticate_token(&token(Algorithm::RS256, "unknown")).await.is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,139 @@
+//! Unit tests for the JWT auth provider
+
+#[cfg(all(test, feature = "jwt"))]
+mod tests {
+    use crate::auth::{AuthError, AuthProvider};
+    use crate::jwt::{ClaimMapping, JwtAuthProvider};
+    use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+    use serde_json::{json, Value};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    
+    const RSA_PRIVATE: &[u8] = include_bytes!("keys/rsa_private.pem");
+    const RSA_PUBLIC: &[u8] = include_bytes!("keys/rsa_public.pem");
+    const EC_PRIVATE: &[u8] = include_bytes!("keys/ec_private.pem");
+    const EC_PUBLIC: &[u8] = include_bytes!("keys/ec_public.pem");
+    
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+    
+    fn claims() -> Value {
+        json!({
+            "sub": "42",
+            "preferred_username": "jane",
+            "roles": ["admin"],
+            "scope": "orders:read orders:write",
+            "iss": "https://sso.example.com",
+            "aud": "my-api",
+            "exp": now() + 300,
+        })
+    }
+    
+    fn sign(algorithm: Algorithm, key: &EncodingKey, claims: &Value) -> String {
+        encode(&Header::new(algorithm), claims, key).unwrap()
+    }
+    
+    // Test HS256 tokens and the default claim mapping
+    #[tokio::test]
+    async fn test_hs256() {
+        let provider = JwtAuthProvider::hs256(b"secret");
+        let token = sign(Algorithm::HS256, &EncodingKey::from_secret(b"secret"), &claims());
+        
+        let user = provider.authenticate_token(&token).await.unwrap();
+        assert_eq!(user.id, "42");
+        assert_eq!(user.username, "jane");
+        assert!(user.has_role("admin"));
+        assert!(user.permissions.is_empty());
+        
+        let forged = sign(Algorithm::HS256, &EncodingKey::from_secret(b"other"), &claims());
+        assert!(matches!(provider.authenticate_token(&forged).await, Err(AuthError::InvalidToken(_))));
+    }
+    
+    // Test RS256 and ES256 tokens signed with local keys
+    #[tokio::test]
+    async fn test_rs256_and_es256() {
+        let provider = JwtAuthProvider::rs256_pem(RSA_PUBLIC).unwrap();
+        let token = sign(Algorithm::RS256, &EncodingKey::from_rsa_pem(RSA_PRIVATE).unwrap(), &claims());
+        assert_eq!(provider.authenticate_token(&token).await.unwrap().id, "42");
+        
+        let provider = JwtAuthProvider::es256_pem(EC_PUBLIC).unwrap();
+        let token = sign(Algorithm::ES256, &EncodingKey::from_ec_pem(EC_PRIVATE).unwrap(), &claims());
+        assert_eq!(provider.authenticate_token(&token).await.unwrap().id, "42");
+        
+        // A token signed with a different algorithm is rejected
+        let token = sign(Algorithm::HS256, &EncodingKey::from_secret(b"secret"), &claims());
+        assert!(provider.authenticate_token(&token).await.is_err());
+    }
+    
+    // Test exp, nbf, iss and aud validation
+    #[tokio::test]
+    async fn test_claim_validation() {
+        let key = EncodingKey::from_secret(b"secret");
+        let provider = JwtAuthProvider::hs256(b"secret")
+            .with_issuer("https://sso.example.com")
+            .with_audience("my-api")
+            .with_leeway(Duration::from_secs(5));
+        
+        assert!(provider.authenticate_token(&sign(Algorithm::HS256, &key, &claims())).await.is_ok());
+        
+        let mut expired = claims();
+        expired["exp"] = json!(now() - 60);
+        let error = provider.authenticate_token(&sign(Algorithm::HS256, &key, &expired)).await.unwrap_err();
+        assert!(matches!(error, AuthError::Expired));
+        
+        // Within the leeway
+        let mut barely_expired = claims();
+        barely_expired["exp"] = json!(now() - 2);
+        assert!(provider.authenticate_token(&sign(Algorithm::HS256, &key, &barely_expired)).await.is_ok());
+        
+        let mut immature = claims();
+        immature["nbf"] = json!(now() + 60);
+        assert!(provider.authenticate_token(&sign(Algorithm::HS256, &key, &immature)).await.is_err());
+        
+        let mut wrong_issuer = claims();
+        wrong_issuer["iss"] = json!("https://evil.example.com");
+        assert!(provider.authenticate_token(&sign(Algorithm::HS256, &key, &wrong_issuer)).await.is_err());
+        
+        let mut wrong_audience = claims();
+        wrong_audience["aud"] = json!("other-api");
+        assert!(provider.authenticate_token(&sign(Algorithm::HS256, &key, &wrong_audience)).await.is_err());
+        
+        // Tokens without the claims must not skip the checks
+        let mut no_issuer = claims();
+        no_issuer.as_object_mut().unwrap().remove("iss");
+        let error = provider.authenticate_token(&sign(Algorithm::HS256, &key, &no_issuer)).await.unwrap_err();
+        assert!(matches!(error, AuthError::InvalidToken(message) if message == "Missing required claim 'iss'"));
+        
+        let mut no_audience = claims();
+        no_audience.as_object_mut().unwrap().remove("aud");
+        let error = provider.authenticate_token(&sign(Algorithm::HS256, &key, &no_audience)).await.unwrap_err();
+        assert!(matches!(error, AuthError::InvalidToken(message) if message == "Missing required claim 'aud'"));
+    }
+    
+    // Test custom and nested claim mappings
+    #[tokio::test]
+    async fn test_claim_mapping() {
+        let key = EncodingKey::from_secret(b"secret");
+        let provider = JwtAuthProvider::hs256(b"secret").with_claims(
+            ClaimMapping::default()
+                .username("email")
+                .roles("realm_access.roles")
+                .permissions("scope"),
+        );
+        
+        let mut claims = claims();
+        claims["email"] = json!("jane@example.com");
+        claims["realm_access"] = json!({ "roles": ["support", "user"] });
+        
+        let user = provider.authenticate_token(&sign(Algorithm::HS256, &key, &claims)).await.unwrap();
+        assert_eq!(user.username, "jane@example.com");
+        assert_eq!(user.roles, vec!["support".to_string(), "user".to_string()]);
+        assert!(user.permissions.contains("orders:read"));
+        assert!(user.permissions.contains("orders:write"));
+        assert!(user.has_scope("orders:read"));
+        
+        let mut anonymous = claims;
+        anonymous.as_object_mut().unwrap().remove("sub");
+        assert!(provider.authenticate_token(&sign(Algorithm::HS256, &key, &anonymous)).await.is_err());
+    }
+}
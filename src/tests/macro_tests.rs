@@ -0,0 +1,203 @@
+//! Unit tests for the require_role and require_permission attribute macros
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{Role, User};
+    use crate::fairing::RocketRoles;
+    use crate::tests::support::{self, TokenProvider};
+    use crate::{rejection, require_permission, require_role};
+    use rocket::http::{Header, Status};
+    use rocket::local::blocking::Client;
+    use rocket::serde::json::{Json, Value};
+    use rocket::{get, post, routes};
+    use std::collections::HashMap;
+    
+    // Declarations whose generated items are tested below
+    mod declared {
+        use crate::define_roles;
+        
+        define_roles! {
+            "support" => ["edit_post", "posts:*"],
+            "trainee" => ["banned"],
+            "admin": "support" => ["manage_users", "posts:read"],
+            permissions => ["beta"]
+        }
+    }
+    
+    #[require_role("admin")]
+    #[get("/admin")]
+    fn admin() -> &'static str {
+        "admin area"
+    }
+    
+    #[require_role(any("admin", all("support", not("trainee"))))]
+    #[get("/tickets")]
+    fn tickets() -> Json<Vec<&'static str>> {
+        Json(vec!["ticket"])
+    }
+    
+    #[require_permission(all("edit_post", not("banned")))]
+    #[post("/posts/<id>")]
+    fn update_post(id: u32) -> Result<String, Status> {
+        if id == 0 {
+            return Err(Status::NotFound);
+        }
+        Ok(format!("{} updated post {}", user.username, id))
+    }
+    
+    #[require_permission("manage_users")]
+    #[get("/users")]
+    fn users() -> &'static str {
+        "users"
+    }
+    
+    #[require_role("admin")]
+    #[get("/async/<id>")]
+    async fn async_admin<'a>(id: &'a str) -> String {
+        rocket::tokio::task::yield_now().await;
+        format!("{} async {}", user.username, id)
+    }
+    
+    #[require_role("support")]
+    #[get("/existing")]
+    fn existing(current: User) -> String {
+        format!("existing {}", current.username)
+    }
+    
+    #[require_role("admin", user = current)]
+    #[get("/named")]
+    fn named() -> String {
+        format!("named {}", current.username)
+    }
+    
+    #[require_role("admin")]
+    #[get("/taken?<user>")]
+    fn taken(user: &str) -> String {
+        format!("taken {}", user)
+    }
+    
+    // Covered by the `posts:*` wildcard grant
+    #[require_permission("posts:publish")]
+    #[post("/publish")]
+    fn publish() -> &'static str {
+        "published"
+    }
+    
+    fn client() -> Client {
+        let provider = TokenProvider::new()
+            .with_user("admin", User::new("1", "admin").with_role("admin"))
+            .with_user("support", User::new("2", "support").with_role("support"))
+            .with_user("trainee", User::new("3", "trainee").with_roles(["support", "trainee"]))
+            .with_user("editor", User::new("4", "editor").with_permission("edit_post"))
+            .with_user("banned", User::new("5", "banned").with_permissions(["edit_post", "banned"]));
+        let mut roles = HashMap::new();
+        roles.insert("admin".to_string(), Role::new("admin").with_permissions(["manage_users", "posts:*"]));
+        
+        support::client(rocket::build()
+            .attach(RocketRoles::new(provider, roles))
+            .register("/", rejection::json_catchers())
+            .mount("/", routes![admin, tickets, update_post, users, async_admin, existing, named, taken, publish]))
+    }
+    
+    fn status(client: &Client, method: &str, uri: &'static str, token: &str) -> Status {
+        let request = match method {
+            "POST" => client.post(uri),
+            _ => client.get(uri),
+        };
+        request
+            .header(Header::new("Authorization", format!("Bearer {}", token)))
+            .dispatch()
+            .status()
+    }
+    
+    // Test role requirements, including boolean expressions
+    #[test]
+    fn test_require_role() {
+        let client = client();
+        
+        assert_eq!(status(&client, "GET", "/admin", "admin"), Status::Ok);
+        assert_eq!(status(&client, "GET", "/admin", "support"), Status::Forbidden);
+        assert_eq!(status(&client, "GET", "/admin", "bogus"), Status::Unauthorized);
+        
+        assert_eq!(status(&client, "GET", "/tickets", "admin"), Status::Ok);
+        assert_eq!(status(&client, "GET", "/tickets", "support"), Status::Ok);
+        assert_eq!(status(&client, "GET", "/tickets", "trainee"), Status::Forbidden);
+    }
+    
+    // Test permission requirements, including role-granted permissions
+    #[test]
+    fn test_require_permission() {
+        let client = client();
+        
+        let response = client.post("/posts/7")
+            .header(Header::new("Authorization", "Bearer editor"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.into_string().unwrap(), "editor updated post 7");
+        
+        assert_eq!(status(&client, "POST", "/posts/0", "editor"), Status::NotFound);
+        assert_eq!(status(&client, "POST", "/posts/7", "banned"), Status::Forbidden);
+        assert_eq!(status(&client, "GET", "/users", "admin"), Status::Ok);
+        assert_eq!(status(&client, "GET", "/users", "editor"), Status::Forbidden);
+        assert_eq!(status(&client, "POST", "/publish", "admin"), Status::Ok);
+        assert_eq!(status(&client, "POST", "/publish", "editor"), Status::Forbidden);
+    }
+    
+    // Test that failed checks are rendered by the catchers
+    #[test]
+    fn test_rejection_rendering() {
+        let client = client();
+        
+        let response = client.get("/tickets")
+            .header(Header::new("Authorization", "Bearer trainee"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        let body: Value = response.into_json().unwrap();
+        assert_eq!(body["error"], "missing_role");
+        assert_eq!(body["message"], "Role any('admin', all('support', not('trainee'))) required");
+    }
+    
+    // Test async handlers, existing User parameters and custom binding names
+    #[test]
+    fn test_handler_signatures() {
+        let client = client();
+        let get = |uri: &'static str, token: &str| {
+            let response = client.get(uri)
+                .header(Header::new("Authorization", format!("Bearer {}", token)))
+                .dispatch();
+            (response.status(), response.into_string().unwrap_or_default())
+        };
+        
+        assert_eq!(get("/async/7", "admin"), (Status::Ok, "admin async 7".to_string()));
+        assert_eq!(get("/async/7", "support").0, Status::Forbidden);
+        assert_eq!(get("/existing", "support"), (Status::Ok, "existing support".to_string()));
+        assert_eq!(get("/existing", "editor").0, Status::Forbidden);
+        assert_eq!(get("/named", "admin"), (Status::Ok, "named admin".to_string()));
+        assert_eq!(get("/taken?user=bob", "admin"), (Status::Ok, "taken bob".to_string()));
+        assert_eq!(get("/taken?user=bob", "support").0, Status::Forbidden);
+    }
+    
+    // Test the enums generated by define_roles!
+    #[test]
+    fn test_generated_enums() {
+        use declared::{PermissionName, RoleName};
+        
+        assert_eq!(RoleName::ALL, &[RoleName::Support, RoleName::Trainee, RoleName::Admin]);
+        assert_eq!(PermissionName::PostsRead.to_string(), "posts:read");
+        assert_eq!(PermissionName::ALL.last(), Some(&PermissionName::Beta));
+        assert_eq!("edit_post".parse::<PermissionName>(), Ok(PermissionName::EditPost));
+        
+        let error = "edti_post".parse::<PermissionName>().unwrap_err();
+        assert_eq!(error.to_string(), "unknown permission 'edti_post'");
+        
+        let json = serde_json::to_string(&[RoleName::Admin, RoleName::Trainee]).unwrap();
+        assert_eq!(json, r#"["admin","trainee"]"#);
+        let roles: Vec<RoleName> = serde_json::from_str(&json).unwrap();
+        assert_eq!(roles, vec![RoleName::Admin, RoleName::Trainee]);
+        assert!(serde_json::from_str::<RoleName>(r#""root""#).is_err());
+        
+        let defined = declared::defined_roles();
+        assert_eq!(defined["admin"].parents, vec!["support".to_string()]);
+        assert!(User::new("1", "admin").with_role("admin").has_role(RoleName::Admin.as_ref()));
+    }
+}
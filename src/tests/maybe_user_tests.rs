@@ -0,0 +1,89 @@
+//! Unit tests for the optional MaybeUser guard
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{AuthError, MaybeUser, User};
+    use crate::fairing::RocketRoles;
+    use crate::rejection;
+    use crate::tests::support::{self, TokenProvider};
+    use rocket::http::{Header, Status};
+    use rocket::local::blocking::Client;
+    use rocket::{get, routes};
+    use std::collections::HashMap;
+    
+    #[get("/")]
+    fn home(user: MaybeUser) -> String {
+        match user.as_ref() {
+            Some(user) => format!("hello {}", user.username),
+            None => "hello stranger".to_string(),
+        }
+    }
+    
+    // `Option<User>` stashes the rejection before Rocket turns it into `None`
+    #[get("/members")]
+    fn members(user: Option<User>) -> Result<String, Status> {
+        match user {
+            Some(user) => Ok(format!("welcome {}", user.username)),
+            None => Err(Status::Forbidden),
+        }
+    }
+    
+    fn provider() -> TokenProvider {
+        TokenProvider::new()
+            .with_user("alice", User::new("1", "alice"))
+            .with_error("outage", AuthError::DatabaseError("connection refused".to_string()))
+    }
+    
+    fn client(fairing: RocketRoles) -> Client {
+        support::client(rocket::build()
+            .attach(fairing)
+            .register("/", rejection::json_catchers())
+            .mount("/", routes![home, members]))
+    }
+    
+    fn get(client: &Client, authorization: Option<&str>) -> (Status, String) {
+        let mut request = client.get("/");
+        if let Some(value) = authorization {
+            request = request.header(Header::new("Authorization", value.to_string()));
+        }
+        let response = request.dispatch();
+        (response.status(), response.into_string().unwrap_or_default())
+    }
+    
+    // Test that missing credentials are anonymous and invalid ones are rejected
+    #[test]
+    fn test_strict() {
+        let client = client(RocketRoles::new(provider(), HashMap::new()));
+        
+        assert_eq!(get(&client, Some("Bearer alice")), (Status::Ok, "hello alice".to_string()));
+        assert_eq!(get(&client, None), (Status::Ok, "hello stranger".to_string()));
+        assert_eq!(get(&client, Some("Bearer bogus")).0, Status::Unauthorized);
+        assert_eq!(get(&client, Some("Basic")).0, Status::Unauthorized);
+        assert_eq!(get(&client, Some("Bearer outage")).0, Status::ServiceUnavailable);
+    }
+    
+    // Test that the lenient mode treats invalid credentials as anonymous, but
+    // not backend failures
+    #[test]
+    fn test_lenient() {
+        let client = client(RocketRoles::new(provider(), HashMap::new()).with_lenient_optional_auth());
+        
+        assert_eq!(get(&client, Some("Bearer alice")), (Status::Ok, "hello alice".to_string()));
+        assert_eq!(get(&client, None), (Status::Ok, "hello stranger".to_string()));
+        assert_eq!(get(&client, Some("Bearer bogus")), (Status::Ok, "hello stranger".to_string()));
+        assert_eq!(get(&client, Some("Bearer outage")).0, Status::ServiceUnavailable);
+    }
+    
+    // Test that an error returned by the route is not explained by the
+    // rejection an optional guard stashed
+    #[test]
+    fn test_route_error() {
+        let client = client(RocketRoles::new(provider(), HashMap::new()));
+        
+        let response = client.get("/members").dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        assert!(response.headers().get_one("WWW-Authenticate").is_none());
+        let body = response.into_string().unwrap_or_default();
+        assert!(!body.contains("missing_credentials"), "{}", body);
+    }
+}
@@ -0,0 +1,100 @@
+//! Unit tests for the Prometheus metrics
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use crate::audit::Decision;
+    use crate::auth::{AuthError, NamedProvider, User};
+    use crate::fairing::RocketRoles;
+    use crate::metrics::{self, Metrics};
+    use crate::require_role;
+    use crate::tests::support::{self, TokenProvider};
+    use rocket::http::{ContentType, Header, Status};
+    use rocket::{get, routes};
+    use std::collections::HashMap;
+    use std::time::Duration;
+    
+    #[require_role("operator")]
+    #[get("/operate")]
+    fn operate() -> &'static str {
+        "operating"
+    }
+    
+    struct Partners;
+    
+    impl NamedProvider for Partners {
+        const NAME: &'static str = "metrics_partners";
+    }
+    
+    struct Unregistered;
+    
+    impl NamedProvider for Unregistered {
+        const NAME: &'static str = "metrics_unregistered";
+    }
+    
+    #[get("/partners")]
+    fn partners(user: User<Partners>) -> String {
+        user.username
+    }
+    
+    #[get("/unregistered")]
+    fn unregistered(user: User<Unregistered>) -> String {
+        user.username
+    }
+    
+    // Test recording into and rendering a registry
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::new();
+        metrics.observe_authentication(None, &Ok(User::new("1", "alice")), Duration::from_millis(3));
+        metrics.observe_authentication(None, &Err(AuthError::UserNotFound), Duration::from_secs(20));
+        metrics.observe_authentication(None, &Err(AuthError::UserNotFound), Duration::from_millis(30));
+        metrics.observe_authentication(Some("sso"), &Err(AuthError::Expired), Duration::from_millis(1));
+        metrics.observe_check("role", Decision::Deny);
+        
+        let text = metrics.render();
+        assert!(text.contains("# TYPE rocket_roles_authentications_total counter\n"));
+        assert!(text.contains("rocket_roles_authentications_total{result=\"success\"} 1\n"));
+        assert!(text.contains("rocket_roles_authentications_total{result=\"user_not_found\"} 2\n"));
+        assert!(text.contains("rocket_roles_provider_duration_seconds_bucket{provider=\"default\",le=\"0.0025\"} 0\n"));
+        assert!(text.contains("rocket_roles_provider_duration_seconds_bucket{provider=\"default\",le=\"0.005\"} 1\n"));
+        assert!(text.contains("rocket_roles_provider_duration_seconds_bucket{provider=\"default\",le=\"0.05\"} 2\n"));
+        assert!(text.contains("rocket_roles_provider_duration_seconds_bucket{provider=\"default\",le=\"5\"} 2\n"));
+        assert!(text.contains("rocket_roles_provider_duration_seconds_bucket{provider=\"default\",le=\"+Inf\"} 3\n"));
+        assert!(text.contains("rocket_roles_provider_duration_seconds_count{provider=\"default\"} 3\n"));
+        assert!(text.contains("rocket_roles_provider_duration_seconds_bucket{provider=\"sso\",le=\"0.001\"} 1\n"));
+        assert!(text.contains("rocket_roles_provider_duration_seconds_count{provider=\"sso\"} 1\n"));
+        assert!(text.contains("rocket_roles_checks_total{kind=\"role\",decision=\"deny\"} 1\n"));
+    }
+    
+    // Test that requests are counted and served from the mounted route
+    #[test]
+    fn test_route() {
+        let provider = TokenProvider::new()
+            .with_user("operator", User::new("1", "operator").with_role("operator"))
+            .with_error("stale", AuthError::Expired);
+        let partners_provider = TokenProvider::new().with_user("partner", User::new("2", "partner"));
+        let client = support::client(rocket::build()
+            .attach(RocketRoles::new(provider, HashMap::new()).with_named_provider(Partners::NAME, partners_provider))
+            .mount("/", routes![operate, partners, unregistered])
+            .mount("/metrics", metrics::routes()));
+        for token in ["operator", "stale"] {
+            client.get("/operate")
+                .header(Header::new("Authorization", format!("Bearer {}", token)))
+                .dispatch();
+        }
+        for uri in ["/partners", "/unregistered"] {
+            client.get(uri).header(Header::new("Authorization", "Bearer partner")).dispatch();
+        }
+        
+        let response = client.get("/metrics").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type().map(|ct| ct.media_type().clone()), Some(ContentType::Plain.media_type().clone()));
+        let text = response.into_string().unwrap();
+        assert!(text.contains("rocket_roles_authentications_total{result=\"success\"}"));
+        assert!(text.contains("rocket_roles_authentications_total{result=\"expired\"}"));
+        assert!(text.contains("rocket_roles_checks_total{kind=\"role\",decision=\"allow\"}"));
+        assert!(text.contains("rocket_roles_provider_duration_seconds_count{provider=\"default\"}"));
+        assert!(text.contains("rocket_roles_provider_duration_seconds_count{provider=\"metrics_partners\"} 1\n"));
+        assert!(!text.contains("metrics_unregistered"));
+    }
+}
@@ -0,0 +1,25 @@
+//! Unit tests for rocket_roles
+
+pub(crate) mod declared;
+#[cfg(test)]
+mod support;
+mod auth_tests;
+mod extract_tests;
+mod fairing_tests;
+mod registry_tests;
+mod config_tests;
+mod jwt_tests;
+mod jwks_tests;
+mod cache_tests;
+mod rejection_tests;
+mod macro_tests;
+mod policy_tests;
+mod permission_tests;
+mod tenant_tests;
+mod scope_tests;
+mod maybe_user_tests;
+mod audit_tests;
+mod metrics_tests;
+mod chain_tests;
+mod provider_tests;
+mod ui_tests;
@@ -0,0 +1,82 @@
+//! Unit tests for wildcard permission matching
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{Role, User};
+    use crate::permission::{grant_matches, PermissionSet};
+    use crate::registry::RoleRegistry;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    
+    // Test segment-aware matching of single grants
+    #[test]
+    fn test_grant_matches() {
+        assert!(grant_matches("posts:edit", "posts:edit"));
+        assert!(grant_matches("posts:*", "posts:edit"));
+        assert!(grant_matches("posts:*", "posts:edit:own"));
+        assert!(!grant_matches("posts:*", "posts"));
+        assert!(!grant_matches("posts:*", "postsx:edit"));
+        assert!(grant_matches("posts:*:read", "posts:drafts:read"));
+        assert!(!grant_matches("posts:*:read", "posts:drafts:write"));
+        assert!(!grant_matches("posts:*:read", "posts:drafts:read:all"));
+        assert!(grant_matches("*", "billing:invoices:read"));
+        assert!(!grant_matches("posts", "posts:edit"));
+    }
+    
+    // Test that the trie agrees with single grant matching
+    #[test]
+    fn test_permission_set() {
+        let grants = ["posts:*", "billing:invoices:read", "users:*:view", "admin"];
+        let set = PermissionSet::from_grants(grants);
+        assert_eq!(set.len(), 4);
+        
+        let permissions = [
+            "posts", "posts:edit", "posts:edit:own", "billing:invoices:read", "billing:invoices:write",
+            "billing:invoices", "users:42:view", "users:42:edit", "admin", "admin:panel",
+        ];
+        for permission in permissions {
+            let expected = grants.iter().any(|grant| grant_matches(grant, permission));
+            assert_eq!(set.matches(permission), expected, "{}", permission);
+        }
+        
+        assert!(PermissionSet::from_grants(["*"]).matches("anything:at:all"));
+        assert!(!PermissionSet::new().matches("posts:edit"));
+    }
+    
+    // Test wildcard grants on roles and users
+    #[test]
+    fn test_wildcard_users() {
+        let mut roles = HashMap::new();
+        roles.insert("editor".to_string(), Role::new("editor").with_permission("posts:*"));
+        roles.insert("chief".to_string(), Role::new("chief").with_parent("editor").with_permission("billing:*:read"));
+        roles.insert("root".to_string(), Role::new("root").with_permission("*"));
+        let registry = Arc::new(RoleRegistry::from_roles(roles));
+        
+        let chief = User::new("1", "chief").with_role("chief").with_registry(registry.clone());
+        assert!(chief.has_permission("posts:delete"));
+        assert!(chief.has_permission("billing:invoices:read"));
+        assert!(!chief.has_permission("billing:invoices:write"));
+        
+        let root = User::new("2", "root").with_role("root").with_registry(registry.clone());
+        assert!(root.has_permission("billing:invoices:write"));
+        
+        let direct = User::new("3", "direct").with_permission("comments:*").with_registry(registry.clone());
+        assert!(direct.has_permission("comments:delete"));
+        assert!(!direct.has_permission("posts:delete"));
+        
+        // Recompiled when the registry changes
+        assert!(registry.grant("editor", "comments:*"));
+        assert!(chief.has_permission("comments:delete"));
+    }
+    
+    // Test lookups against a large catalog
+    #[test]
+    fn test_large_catalog() {
+        let grants: Vec<String> = (0..5_000).map(|i| format!("resource{}:action{}", i, i % 7)).collect();
+        let set = PermissionSet::from_grants(&grants);
+        
+        assert!(set.matches("resource4321:action2"));
+        assert!(!set.matches("resource4321:action3"));
+        assert!(!set.matches("resource5000:action0"));
+    }
+}
@@ -0,0 +1,94 @@
+//! Unit tests for resource-scoped policies
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::User;
+    use crate::fairing::RocketRoles;
+    use crate::policy::{Action, Authorize, Policy};
+    use crate::rejection::{self, AuthRejection};
+    use crate::tests::support::{self, TokenProvider};
+    use async_trait::async_trait;
+    use rocket::http::{Header, Status};
+    use rocket::serde::json::Value;
+    use rocket::{put, routes};
+    use std::collections::HashMap;
+    
+    struct Post {
+        author_id: String,
+    }
+    
+    #[async_trait]
+    impl Authorize for Post {
+        async fn authorize(&self, user: &User, action: &Action) -> bool {
+            match action {
+                Action::Read => true,
+                Action::Edit => self.author_id == user.id || user.has_permission("edit_any_post"),
+                _ => false,
+            }
+        }
+    }
+    
+    /// Only lets users act on posts while the site is not read-only
+    struct ReadOnlyPolicy;
+    
+    #[async_trait]
+    impl Policy<Post> for ReadOnlyPolicy {
+        async fn allows(&self, _user: &User, _post: &Post, action: &Action) -> bool {
+            *action == Action::Read
+        }
+    }
+    
+    #[put("/posts/<id>")]
+    async fn edit_post(id: u32, user: User) -> Result<String, AuthRejection> {
+        let post = Post { author_id: "1".to_string() };
+        user.can(Action::Edit, &post).await?;
+        Ok(format!("{} edited post {}", user.username, id))
+    }
+    
+    // Test checks against a resource and a separate policy
+    #[rocket::async_test]
+    async fn test_can() {
+        let post = Post { author_id: "1".to_string() };
+        let author = User::new("1", "author");
+        let other = User::new("2", "other");
+        
+        assert!(author.can(Action::Edit, &post).await.is_ok());
+        assert!(other.can(Action::Read, &post).await.is_ok());
+        assert!(author.can(Action::Delete, &post).await.is_err());
+        
+        let denied = other.can(Action::Edit, &post).await.unwrap_err();
+        assert_eq!(denied.status(), Status::Forbidden);
+        assert_eq!(denied.to_string(), "Not allowed to edit this resource");
+        
+        assert!(author.can_with(&ReadOnlyPolicy, Action::Read, &post).await.is_ok());
+        let denied = author.can_with(&ReadOnlyPolicy, Action::custom("publish"), &post).await.unwrap_err();
+        assert_eq!(denied.to_string(), "Not allowed to publish this resource");
+    }
+    
+    // Test that denied checks in handlers are rendered by the catchers
+    #[test]
+    fn test_denied_handler() {
+        let provider = TokenProvider::new()
+            .with_user("author", User::new("1", "author"))
+            .with_user("other", User::new("2", "other"))
+            .with_user("moderator", User::new("3", "moderator").with_permission("edit_any_post"));
+        let client = support::client(rocket::build()
+            .attach(RocketRoles::new(provider, HashMap::new()))
+            .register("/", rejection::json_catchers())
+            .mount("/", routes![edit_post]));
+        let edit = |token: &str| {
+            client.put("/posts/7")
+                .header(Header::new("Authorization", format!("Bearer {}", token)))
+                .dispatch()
+        };
+        
+        assert_eq!(edit("author").into_string().unwrap(), "author edited post 7");
+        assert_eq!(edit("moderator").status(), Status::Ok);
+        
+        let response = edit("other");
+        assert_eq!(response.status(), Status::Forbidden);
+        let body: Value = response.into_json().unwrap();
+        assert_eq!(body["error"], "action_denied");
+        assert_eq!(body["message"], "Not allowed to edit this resource");
+    }
+}
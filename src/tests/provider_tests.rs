@@ -0,0 +1,164 @@
+//! Unit tests for named auth providers
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{
+        register_named_auth_provider, unregister_named_auth_provider, AuthError, AuthProvider, MaybeUser, NamedProvider,
+        User,
+    };
+    use crate::fairing::RocketRoles;
+    use crate::require_role;
+    use async_trait::async_trait;
+    use rocket::http::{Header, Status};
+    use rocket::local::blocking::Client;
+    use rocket::{get, routes};
+    use std::collections::HashMap;
+    
+    // Provider that accepts a single token
+    struct SingleTokenProvider {
+        token: &'static str,
+        user: fn() -> User,
+    }
+    
+    #[async_trait]
+    impl AuthProvider for SingleTokenProvider {
+        async fn authenticate_token(&self, token: &str) -> Result<User, AuthError> {
+            if token == self.token {
+                Ok((self.user)())
+            } else {
+                Err(AuthError::InvalidToken("Unknown token".to_string()))
+            }
+        }
+    }
+    
+    fn customers() -> SingleTokenProvider {
+        SingleTokenProvider { token: "api-key", user: || User::new("1", "customer").with_role("customer") }
+    }
+    
+    fn sso() -> SingleTokenProvider {
+        SingleTokenProvider { token: "sso-ticket", user: || User::new("2", "staff").with_role("staff") }
+    }
+    
+    struct Sso;
+    
+    impl NamedProvider for Sso {
+        const NAME: &'static str = "sso";
+    }
+    
+    struct Partners;
+    
+    impl NamedProvider for Partners {
+        const NAME: &'static str = "provider_tests_partners";
+    }
+    
+    struct Missing;
+    
+    impl NamedProvider for Missing {
+        const NAME: &'static str = "missing";
+    }
+    
+    #[get("/me")]
+    fn me(user: User) -> String {
+        user.username
+    }
+    
+    #[get("/staff/me")]
+    fn staff_me(user: User<Sso>) -> String {
+        user.username
+    }
+    
+    #[get("/staff/maybe")]
+    fn staff_maybe(user: MaybeUser<Sso>) -> String {
+        match user.into_inner() {
+            Some(user) => user.username,
+            None => "anonymous".to_string(),
+        }
+    }
+    
+    #[require_role("staff", provider = "sso")]
+    #[get("/staff/admin")]
+    fn staff_admin() -> String {
+        format!("admin {}", user.username)
+    }
+    
+    #[require_role("staff")]
+    #[get("/staff/typed")]
+    fn staff_typed(current: User<Sso>) -> String {
+        format!("typed {}", current.into_user().username)
+    }
+    
+    #[get("/partners/me")]
+    fn partner_me(user: User<Partners>) -> String {
+        user.username
+    }
+    
+    #[get("/missing")]
+    fn missing(user: User<Missing>) -> String {
+        user.username
+    }
+    
+    fn client() -> Client {
+        let rocket = rocket::build()
+            .attach(RocketRoles::new(customers(), HashMap::new()).with_named_provider("sso", sso()))
+            .mount("/", routes![me, staff_me, staff_maybe, staff_admin, staff_typed, partner_me, missing]);
+        Client::untracked(rocket).expect("valid rocket instance")
+    }
+    
+    fn get(client: &Client, uri: &'static str, token: &str) -> (Status, String) {
+        let response = client.get(uri)
+            .header(Header::new("Authorization", format!("Bearer {}", token)))
+            .dispatch();
+        (response.status(), response.into_string().unwrap_or_default())
+    }
+    
+    // Test that routes authenticate with the provider they select
+    #[test]
+    fn test_named_providers() {
+        let client = client();
+        
+        assert_eq!(get(&client, "/me", "api-key"), (Status::Ok, "customer".to_string()));
+        assert_eq!(get(&client, "/me", "sso-ticket").0, Status::Unauthorized);
+        
+        assert_eq!(get(&client, "/staff/me", "sso-ticket"), (Status::Ok, "staff".to_string()));
+        assert_eq!(get(&client, "/staff/me", "api-key").0, Status::Unauthorized);
+        
+        assert_eq!(get(&client, "/staff/admin", "sso-ticket"), (Status::Ok, "admin staff".to_string()));
+        assert_eq!(get(&client, "/staff/admin", "api-key").0, Status::Unauthorized);
+        
+        assert_eq!(get(&client, "/staff/typed", "sso-ticket"), (Status::Ok, "typed staff".to_string()));
+        assert_eq!(get(&client, "/staff/typed", "api-key").0, Status::Unauthorized);
+        
+        assert_eq!(get(&client, "/staff/maybe", "sso-ticket"), (Status::Ok, "staff".to_string()));
+        assert_eq!(get(&client, "/staff/maybe", "api-key").0, Status::Unauthorized);
+        let response = client.get("/staff/maybe").dispatch();
+        assert_eq!(response.into_string().unwrap_or_default(), "anonymous");
+    }
+    
+    // Test the fallback to globally registered providers, its precedence
+    // and unknown names
+    #[test]
+    fn test_registration() {
+        register_named_auth_provider(Partners::NAME, SingleTokenProvider {
+            token: "partner-key",
+            user: || User::new("3", "partner"),
+        });
+        register_named_auth_provider(Sso::NAME, SingleTokenProvider {
+            token: "global-ticket",
+            user: || User::new("4", "global"),
+        });
+        let client = client();
+        
+        assert_eq!(get(&client, "/partners/me", "partner-key"), (Status::Ok, "partner".to_string()));
+        assert_eq!(get(&client, "/partners/me", "api-key").0, Status::Unauthorized);
+        assert_eq!(get(&client, "/missing", "api-key").0, Status::InternalServerError);
+        
+        // The fairing's provider wins over the global one
+        assert_eq!(get(&client, "/staff/me", "global-ticket").0, Status::Unauthorized);
+        assert_eq!(get(&client, "/staff/me", "sso-ticket"), (Status::Ok, "staff".to_string()));
+        
+        assert!(unregister_named_auth_provider(Sso::NAME).is_some());
+        assert!(unregister_named_auth_provider(Partners::NAME).is_some());
+        assert!(unregister_named_auth_provider(Partners::NAME).is_none());
+        assert_eq!(get(&client, "/partners/me", "partner-key").0, Status::InternalServerError);
+    }
+}
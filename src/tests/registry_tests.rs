@@ -0,0 +1,81 @@
+//! Unit tests for the runtime role registry
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{Role, User};
+    use crate::registry::{RoleChange, RoleRegistry};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    
+    fn registry() -> Arc<RoleRegistry> {
+        let mut roles = HashMap::new();
+        roles.insert("user".to_string(), Role::new("user").with_permission("view_profile"));
+        roles.insert("support".to_string(), Role::new("support").with_parent("user"));
+        Arc::new(RoleRegistry::from_roles(roles))
+    }
+    
+    // Test that grants and revocations are visible to existing users
+    #[test]
+    fn test_grant_and_revoke() {
+        let registry = registry();
+        let support = User::new("1", "support").with_role("support").with_registry(registry.clone());
+        
+        assert!(support.has_permission("view_profile"));
+        assert!(!support.has_permission("respond_to_tickets"));
+        
+        assert!(registry.grant("support", "respond_to_tickets"));
+        assert!(!registry.grant("support", "respond_to_tickets"));
+        assert!(support.has_permission("respond_to_tickets"));
+        
+        // Granting to a parent is inherited
+        assert!(registry.grant("user", "edit_profile"));
+        assert!(support.has_permission("edit_profile"));
+        
+        assert!(registry.revoke("support", "respond_to_tickets"));
+        assert!(!support.has_permission("respond_to_tickets"));
+        assert!(!registry.grant("missing", "anything"));
+    }
+    
+    // Test adding, removing and replacing roles
+    #[test]
+    fn test_add_remove_replace() {
+        let registry = registry();
+        let admin = User::new("1", "admin").with_role("admin").with_registry(registry.clone());
+        
+        assert!(admin.all_permissions().is_empty());
+        
+        assert!(registry.add_role(Role::new("admin").with_parent("support").with_permission("manage")).is_none());
+        assert!(admin.has_permission("manage"));
+        assert!(admin.has_permission("view_profile"));
+        
+        assert!(registry.remove_role("admin").is_some());
+        assert!(registry.remove_role("admin").is_none());
+        assert!(!admin.has_permission("manage"));
+        
+        let mut roles = HashMap::new();
+        roles.insert("admin".to_string(), Role::new("admin").with_permission("everything"));
+        registry.replace_all(roles);
+        assert!(admin.has_permission("everything"));
+        assert!(!registry.roles().contains_key("user"));
+    }
+    
+    // Test that subscribers are notified of every change
+    #[test]
+    fn test_change_notifications() {
+        let registry = registry();
+        let mut changes = registry.subscribe();
+        
+        registry.grant("support", "respond_to_tickets");
+        registry.revoke("support", "missing_permission");
+        registry.remove_role("user");
+        registry.replace_all(HashMap::new());
+        
+        assert_eq!(changes.try_recv().unwrap(), RoleChange::Granted {
+            role: "support".to_string(),
+            permission: "respond_to_tickets".to_string(),
+        });
+        assert_eq!(changes.try_recv().unwrap(), RoleChange::Removed("user".to_string()));
+        assert_eq!(changes.try_recv().unwrap(), RoleChange::Replaced);
+        assert!(changes.try_recv().is_err());
+    }
+}
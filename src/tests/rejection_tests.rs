@@ -0,0 +1,194 @@
+//! Unit tests for structured rejections and catchers
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{AuthError, AuthProvider, User};
+    use crate::fairing::RocketRoles;
+    use crate::rejection::{self, AuthRejection, RejectionRenderer};
+    use async_trait::async_trait;
+    use rocket::http::{ContentType, Header, Status};
+    use rocket::local::blocking::Client;
+    use rocket::request::Request;
+    use rocket::response::Response;
+    use rocket::serde::json::Value;
+    use rocket::{get, routes, Catcher};
+    use std::collections::HashMap;
+    use std::io::Cursor;
+    use std::time::Duration;
+    
+    struct RejectAll;
+    
+    #[async_trait]
+    impl AuthProvider for RejectAll {
+        async fn authenticate_token(&self, _token: &str) -> Result<User, AuthError> {
+            Err(AuthError::InvalidToken("Token revoked".to_string()))
+        }
+    }
+    
+    #[get("/me")]
+    fn me(user: User) -> String {
+        user.username
+    }
+    
+    fn client(catchers: Vec<Catcher>) -> Client {
+        let rocket = rocket::build()
+            .attach(RocketRoles::new(RejectAll, HashMap::new()))
+            .register("/", catchers)
+            .mount("/", routes![me]);
+        Client::untracked(rocket).expect("valid rocket instance")
+    }
+    
+    // Test the status and code of each rejection
+    #[test]
+    fn test_rejection_mapping() {
+        assert_eq!(AuthRejection::MissingCredentials.status(), Status::Unauthorized);
+        assert_eq!(AuthRejection::ProviderUnregistered.status(), Status::InternalServerError);
+        assert_eq!(AuthRejection::MissingRole("'admin'".to_string()).status(), Status::Forbidden);
+        assert_eq!(AuthRejection::MissingPermission("'x'".to_string()).code(), "missing_permission");
+        assert_eq!(AuthRejection::MissingRole("'admin'".to_string()).to_string(), "Role 'admin' required");
+    }
+    
+    // Test the JSON catchers
+    #[test]
+    fn test_json_catchers() {
+        let client = client(rejection::json_catchers());
+        
+        let response = client.get("/me").dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+        let body: Value = response.into_json().unwrap();
+        assert_eq!(body["error"], "missing_credentials");
+        assert_eq!(body["status"], 401);
+        
+        let response = client.get("/me")
+            .header(Header::new("Authorization", "Basic abc"))
+            .dispatch();
+        let body: Value = response.into_json().unwrap();
+        assert_eq!(body["error"], "malformed_credentials");
+        
+        let response = client.get("/me")
+            .header(Header::new("Authorization", "Bearer abc"))
+            .dispatch();
+        let body: Value = response.into_json().unwrap();
+        assert_eq!(body["error"], "invalid_token");
+        assert_eq!(body["message"], "Authentication failed: Invalid token: Token revoked");
+    }
+    
+    // Test the RFC 7807 problem+json catchers
+    #[test]
+    fn test_problem_catchers() {
+        let client = client(rejection::problem_catchers());
+        
+        let response = client.get("/me").dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+        assert_eq!(response.content_type(), Some(ContentType::new("application", "problem+json")));
+        let body: Value = response.into_json().unwrap();
+        assert_eq!(body["type"], "about:blank");
+        assert_eq!(body["title"], "Unauthorized");
+        assert_eq!(body["status"], 401);
+        assert_eq!(body["detail"], "Authentication token is required");
+        assert_eq!(body["instance"], "/me");
+    }
+    
+    #[derive(Clone)]
+    struct PlainRenderer;
+    
+    impl RejectionRenderer for PlainRenderer {
+        fn render(&self, status: Status, rejection: Option<&AuthRejection>, _request: &Request<'_>) -> Response<'static> {
+            let body = rejection.map(|r| r.code()).unwrap_or("unknown").to_string();
+            Response::build()
+                .status(status)
+                .sized_body(body.len(), Cursor::new(body))
+                .finalize()
+        }
+    }
+    
+    // Test customizing the rendering
+    #[test]
+    fn test_custom_renderer() {
+        let client = client(rejection::catchers(PlainRenderer));
+        
+        let response = client.get("/me").dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+        assert_eq!(response.into_string().unwrap(), "missing_credentials");
+    }
+    
+    struct ExpiringProvider;
+    
+    #[async_trait]
+    impl AuthProvider for ExpiringProvider {
+        async fn authenticate_token(&self, token: &str) -> Result<User, AuthError> {
+            match token {
+                "expired" => Err(AuthError::Expired),
+                "busy" => Err(AuthError::RateLimited { retry_after: Some(Duration::from_millis(1500)) }),
+                "outage" => Err(AuthError::DatabaseError("connection refused".to_string())),
+                _ => Err(AuthError::InvalidToken("Unknown \"token\"".to_string())),
+            }
+        }
+    }
+    
+    // Test WWW-Authenticate challenges for each kind of 401
+    #[test]
+    fn test_challenges() {
+        let rocket = rocket::build()
+            .attach(RocketRoles::new(ExpiringProvider, HashMap::new()).with_realm("api"))
+            .mount("/", routes![me]);
+        let client = Client::untracked(rocket).expect("valid rocket instance");
+        let challenge = |authorization: Option<&'static str>| {
+            let mut request = client.get("/me");
+            if let Some(authorization) = authorization {
+                request = request.header(Header::new("Authorization", authorization));
+            }
+            let response = request.dispatch();
+            assert_eq!(response.status(), Status::Unauthorized);
+            response.headers().get_one("WWW-Authenticate").map(str::to_string)
+        };
+        
+        assert_eq!(challenge(None).as_deref(), Some(r#"Bearer realm="api""#));
+        assert_eq!(
+            challenge(Some("Basic abc")).as_deref(),
+            Some(r#"Bearer realm="api", error="invalid_request", error_description="Invalid authorization format""#)
+        );
+        assert_eq!(
+            challenge(Some("Bearer expired")).as_deref(),
+            Some(r#"Bearer realm="api", error="invalid_token", error_description="Token has expired""#)
+        );
+        assert_eq!(
+            challenge(Some("Bearer forged")).as_deref(),
+            Some(r#"Bearer realm="api", error="invalid_token", error_description="Unknown \"token\"""#)
+        );
+        
+        assert_eq!(AuthRejection::InvalidToken(AuthError::Expired).code(), "token_expired");
+        assert_eq!(AuthRejection::MissingCredentials.challenge(None).as_deref(), Some("Bearer"));
+        assert_eq!(AuthRejection::ProviderUnregistered.challenge(Some("api")), None);
+    }
+    
+    // Test that rate limiting and backend failures are not reported as 401
+    #[test]
+    fn test_error_statuses() {
+        let rocket = rocket::build()
+            .attach(RocketRoles::new(ExpiringProvider, HashMap::new()))
+            .register("/", rejection::json_catchers())
+            .mount("/", routes![me]);
+        let client = Client::untracked(rocket).expect("valid rocket instance");
+        let get = |token: &str| {
+            client.get("/me")
+                .header(Header::new("Authorization", format!("Bearer {}", token)))
+                .dispatch()
+        };
+        
+        let response = get("busy");
+        assert_eq!(response.status(), Status::TooManyRequests);
+        assert_eq!(response.headers().get_one("Retry-After"), Some("2"));
+        assert_eq!(response.headers().get_one("WWW-Authenticate"), None);
+        let body: Value = response.into_json().unwrap();
+        assert_eq!(body["error"], "rate_limited");
+        
+        let response = get("outage");
+        assert_eq!(response.status(), Status::ServiceUnavailable);
+        assert_eq!(response.headers().get_one("WWW-Authenticate"), None);
+        let body: Value = response.into_json().unwrap();
+        assert_eq!(body["error"], "provider_error");
+        assert_eq!(body["message"], "Authentication is temporarily unavailable");
+    }
+}
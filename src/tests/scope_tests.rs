@@ -0,0 +1,91 @@
+//! Unit tests for OAuth2 scope enforcement
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::User;
+    use crate::fairing::RocketRoles;
+    use crate::rejection::{self, AuthRejection};
+    use crate::require_scope;
+    use crate::tests::support::{self, TokenProvider};
+    use rocket::http::{Header, Status};
+    use rocket::local::blocking::Client;
+    use rocket::serde::json::Value;
+    use rocket::{get, routes, Catcher};
+    use std::collections::HashMap;
+    
+    #[require_scope("orders:read")]
+    #[get("/orders")]
+    fn orders() -> String {
+        format!("orders for {}", user.username)
+    }
+    
+    #[require_scope(all("orders:write", not("readonly")))]
+    #[get("/orders/new")]
+    fn new_order() -> &'static str {
+        "created"
+    }
+    
+    fn client(catchers: Vec<Catcher>) -> Client {
+        let provider = TokenProvider::new()
+            .with_user("reader", User::new("1", "reader").with_scopes(["orders:read", "profile"]))
+            .with_user("profile", User::new("2", "profile").with_scope("profile"));
+        support::client(rocket::build()
+            .attach(RocketRoles::new(provider, HashMap::new()))
+            .register("/", catchers)
+            .mount("/", routes![orders, new_order]))
+    }
+    
+    // Test the RFC 6750 challenge for insufficient scopes
+    #[test]
+    fn test_challenge() {
+        let rejection = AuthRejection::InsufficientScope {
+            requirement: "'orders:read'".to_string(),
+            scopes: vec!["orders:read".to_string(), "say \"hi\"".to_string()],
+        };
+        assert_eq!(rejection.status(), Status::Forbidden);
+        assert_eq!(
+            rejection.challenge(None).unwrap(),
+            r#"Bearer error="insufficient_scope", scope="orders:read say \"hi\"""#
+        );
+        assert_eq!(AuthRejection::MissingRole("'admin'".to_string()).challenge(None), None);
+    }
+    
+    // Test scope requirements with and without the catchers
+    #[test]
+    fn test_require_scope() {
+        for catchers in [rejection::json_catchers(), Vec::new()] {
+            let client = client(catchers);
+            let get = |uri: &'static str, token: &str| {
+                client.get(uri)
+                    .header(Header::new("Authorization", format!("Bearer {}", token)))
+                    .dispatch()
+            };
+            
+            let response = get("/orders", "reader");
+            assert_eq!(response.status(), Status::Ok);
+            assert_eq!(response.headers().get_one("WWW-Authenticate"), None);
+            assert_eq!(response.into_string().unwrap(), "orders for reader");
+            
+            let response = get("/orders", "profile");
+            assert_eq!(response.status(), Status::Forbidden);
+            assert_eq!(
+                response.headers().get_one("WWW-Authenticate"),
+                Some(r#"Bearer error="insufficient_scope", scope="orders:read""#)
+            );
+            
+            let response = get("/orders/new", "reader");
+            assert_eq!(
+                response.headers().get_one("WWW-Authenticate"),
+                Some(r#"Bearer error="insufficient_scope", scope="orders:write""#)
+            );
+        }
+        
+        let client = client(rejection::json_catchers());
+        let response = client.get("/orders")
+            .header(Header::new("Authorization", "Bearer profile"))
+            .dispatch();
+        let body: Value = response.into_json().unwrap();
+        assert_eq!(body["error"], "insufficient_scope");
+        assert_eq!(body["message"], "Scope 'orders:read' required");
+    }
+}
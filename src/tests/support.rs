@@ -0,0 +1,48 @@
+//! Fixtures shared by the unit tests
+
+use crate::auth::{AuthError, AuthProvider, User};
+use async_trait::async_trait;
+use rocket::local::blocking::Client;
+use rocket::{Build, Rocket};
+use std::collections::HashMap;
+
+/// Authenticates tokens by looking them up in a fixed table
+///
+/// Tokens missing from the table are rejected as invalid.
+#[derive(Default)]
+pub(crate) struct TokenProvider {
+    tokens: HashMap<&'static str, Result<User, AuthError>>,
+}
+
+impl TokenProvider {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Authenticate `token` as `user`
+    pub(crate) fn with_user(mut self, token: &'static str, user: User) -> Self {
+        self.tokens.insert(token, Ok(user));
+        self
+    }
+
+    /// Fail `token` with `error`
+    pub(crate) fn with_error(mut self, token: &'static str, error: AuthError) -> Self {
+        self.tokens.insert(token, Err(error));
+        self
+    }
+}
+
+#[async_trait]
+impl AuthProvider for TokenProvider {
+    async fn authenticate_token(&self, token: &str) -> Result<User, AuthError> {
+        match self.tokens.get(token) {
+            Some(result) => result.clone(),
+            None => Err(AuthError::InvalidToken("Invalid token".to_string())),
+        }
+    }
+}
+
+/// Build a blocking client for the given instance
+pub(crate) fn client(rocket: Rocket<Build>) -> Client {
+    Client::untracked(rocket).expect("valid rocket instance")
+}
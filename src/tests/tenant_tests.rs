@@ -0,0 +1,116 @@
+//! Unit tests for tenant-scoped roles
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::User;
+    use crate::fairing::RocketRoles;
+    use crate::tests::support::{self, TokenProvider};
+    use crate::{rejection, require_permission, require_role};
+    use rocket::http::uri::Host;
+    use rocket::http::{Header, Status};
+    use rocket::local::blocking::Client;
+    use rocket::serde::json::Value;
+    use rocket::{get, routes};
+    
+    mod declared {
+        use crate::define_roles;
+        
+        define_roles! {
+            "viewer" => [],
+            "admin" => ["billing:*"]
+        }
+    }
+    
+    #[require_role("admin", tenant = "<org_id>")]
+    #[get("/orgs/<org_id>/settings")]
+    fn settings(org_id: &str) -> String {
+        format!("{} settings for {}", user.username, org_id)
+    }
+    
+    #[require_role(any("admin", "viewer"), tenant = header("X-Org-Id"))]
+    #[get("/reports")]
+    fn reports() -> &'static str {
+        "reports"
+    }
+    
+    #[require_permission("billing:read", tenant = subdomain("example.com"))]
+    #[get("/billing")]
+    fn billing() -> &'static str {
+        "billing"
+    }
+    
+    fn client() -> Client {
+        let provider = TokenProvider::new()
+            .with_user("alice", User::new("1", "alice")
+                .with_tenant_role("acme", "admin")
+                .with_tenant_role("globex", "viewer"))
+            .with_user("root", User::new("2", "root").with_role("admin"));
+        support::client(rocket::build()
+            .attach(RocketRoles::new(provider, declared::defined_roles()))
+            .register("/", rejection::json_catchers())
+            .mount("/api", routes![settings, reports, billing]))
+    }
+    
+    // Test role lookups within tenants
+    #[test]
+    fn test_roles_in() {
+        let user = User::new("1", "alice")
+            .with_role("employee")
+            .with_tenant_roles("acme", ["admin", "billing"]);
+        
+        assert_eq!(user.roles_in("acme"), vec!["employee", "admin", "billing"]);
+        assert_eq!(user.roles_in("globex"), vec!["employee"]);
+        assert!(user.has_role_in("acme", "admin"));
+        assert!(!user.has_role_in("globex", "admin"));
+        assert!(!user.has_role("admin"));
+    }
+    
+    // Test tenants read from a path segment below the mount point
+    #[test]
+    fn test_path_tenant() {
+        let client = client();
+        let get = |uri: &'static str, token: &str| {
+            client.get(uri)
+                .header(Header::new("Authorization", format!("Bearer {}", token)))
+                .dispatch()
+        };
+        
+        assert_eq!(get("/api/orgs/acme/settings", "alice").into_string().unwrap(), "alice settings for acme");
+        assert_eq!(get("/api/orgs/acme/settings", "root").status(), Status::Ok);
+        
+        let response = get("/api/orgs/globex/settings", "alice");
+        assert_eq!(response.status(), Status::Forbidden);
+        let body: Value = response.into_json().unwrap();
+        assert_eq!(body["message"], "Role 'admin' in tenant 'globex' required");
+    }
+    
+    // Test tenants read from a header and from the subdomain
+    #[test]
+    fn test_header_and_subdomain_tenants() {
+        let client = client();
+        let get = |uri: &'static str, headers: &[(&'static str, &'static str)]| {
+            let mut request = client.get(uri).header(Header::new("Authorization", "Bearer alice"));
+            for (name, value) in headers {
+                if *name == "Host" {
+                    request.inner_mut().set_host(Host::parse(value).unwrap());
+                } else {
+                    request = request.header(Header::new(*name, *value));
+                }
+            }
+            request.dispatch()
+        };
+        
+        assert_eq!(get("/api/reports", &[("X-Org-Id", "globex")]).status(), Status::Ok);
+        assert_eq!(get("/api/reports", &[("X-Org-Id", "initech")]).status(), Status::Forbidden);
+        
+        let response = get("/api/reports", &[]);
+        assert_eq!(response.status(), Status::BadRequest);
+        let body: Value = response.into_json().unwrap();
+        assert_eq!(body["error"], "missing_tenant");
+        assert_eq!(body["message"], "Tenant required from header X-Org-Id");
+        
+        assert_eq!(get("/api/billing", &[("Host", "acme.example.com")]).status(), Status::Ok);
+        assert_eq!(get("/api/billing", &[("Host", "globex.example.com")]).status(), Status::Forbidden);
+        assert_eq!(get("/api/billing", &[("Host", "example.com")]).status(), Status::BadRequest);
+    }
+}
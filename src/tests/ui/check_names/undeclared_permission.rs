@@ -0,0 +1,21 @@
+use rocket::get;
+use rocket_roles::require_permission;
+
+mod roles {
+    use rocket_roles::define_roles;
+
+    define_roles! {
+        "admin" => ["manage_users", "posts:*"],
+        permissions => ["posts:publish"]
+    }
+}
+
+use roles::__rocket_roles_declared;
+
+#[require_permission(all("posts:publish", "manage_user"))]
+#[get("/users")]
+fn users() -> &'static str {
+    "users"
+}
+
+fn main() {}
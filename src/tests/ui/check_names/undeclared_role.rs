@@ -0,0 +1,15 @@
+use rocket::get;
+use rocket_roles::{define_roles, require_role};
+
+define_roles! {
+    "admin" => ["manage_users"],
+    "support" => []
+}
+
+#[require_role(any("support", "amdin"))]
+#[get("/dashboard")]
+fn dashboard() -> &'static str {
+    "dashboard"
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use rocket::get;
+use rocket_roles::{define_roles, require_permission};
+
+define_roles! {
+    "root" => ["*"],
+    "editor" => ["edit_post"]
+}
+
+#[require_permission("edti_post")]
+#[get("/posts")]
+fn posts() -> &'static str {
+    "posts"
+}
+
+fn main() {}
@@ -0,0 +1,32 @@
+use rocket::get;
+use rocket_roles::{define_roles, require_role};
+
+define_roles! {
+    "admin" => []
+}
+
+mod models {
+    use rocket::request::{FromRequest, Outcome, Request};
+
+    pub struct User;
+
+    #[rocket::async_trait]
+    impl<'r> FromRequest<'r> for User {
+        type Error = ();
+
+        async fn from_request(_request: &'r Request<'_>) -> Outcome<Self, ()> {
+            Outcome::Success(User)
+        }
+    }
+}
+
+use models::User;
+
+#[require_role("admin")]
+#[get("/dashboard")]
+fn dashboard(user: User) -> &'static str {
+    let _ = user;
+    "dashboard"
+}
+
+fn main() {}
@@ -0,0 +1,36 @@
+#![allow(unused_imports)]
+
+use rocket::get;
+use rocket_roles::{require_permission, require_role};
+
+#[require_role(any())]
+#[get("/empty")]
+fn empty() -> &'static str {
+    "empty"
+}
+
+#[require_role(not("admin", "support"))]
+#[get("/not")]
+fn two_negated() -> &'static str {
+    "not"
+}
+
+#[require_permission(either("posts:read", "posts:write"))]
+#[get("/either")]
+fn unknown_combinator() -> &'static str {
+    "either"
+}
+
+#[require_permission(all("posts:read" "posts:write"))]
+#[get("/comma")]
+fn missing_comma() -> &'static str {
+    "comma"
+}
+
+#[require_role(admin)]
+#[get("/ident")]
+fn bare_name() -> &'static str {
+    "ident"
+}
+
+fn main() {}
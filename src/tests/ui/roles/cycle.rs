@@ -0,0 +1,8 @@
+use rocket_roles::define_roles;
+
+define_roles! {
+    "a": "b" => ["read"],
+    "b": "a" => ["write"]
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use rocket_roles::define_roles;
+
+define_roles! {
+    "user" => ["view_profile"],
+    "admin": "usr" => ["delete_user"]
+}
+
+fn main() {}
@@ -0,0 +1,43 @@
+use rocket::get;
+use rocket_roles::{define_roles, require_permission, require_role};
+
+define_roles! {
+    "admin" => ["manage_users"],
+    "support" => []
+}
+
+mod models {
+    use rocket::request::{FromRequest, Outcome, Request};
+
+    pub struct User {
+        pub name: String,
+    }
+
+    #[rocket::async_trait]
+    impl<'r> FromRequest<'r> for User {
+        type Error = ();
+
+        async fn from_request(_request: &'r Request<'_>) -> Outcome<Self, ()> {
+            Outcome::Success(User { name: "owner".to_string() })
+        }
+    }
+}
+
+#[require_role("admin")]
+#[require_role("support")]
+#[require_permission("manage_users")]
+#[get("/users")]
+fn users(user: rocket_roles::User) -> String {
+    user.username
+}
+
+// An application type named `User` is left alone
+#[require_role("admin")]
+#[get("/owner")]
+fn owner(owner: models::User) -> String {
+    format!("{} {}", user.username, owner.name)
+}
+
+fn main() {
+    let _ = rocket::routes![users, owner];
+}
@@ -0,0 +1,10 @@
+use rocket::get;
+use rocket_roles::require_role;
+
+#[require_role("admin")]
+#[get("/dashboard")]
+fn dashboard() -> &'static str {
+    "dashboard"
+}
+
+fn main() {}
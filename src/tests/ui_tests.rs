@@ -0,0 +1,50 @@
+//! Compile-fail tests for the diagnostics of the macros
+
+#[cfg(test)]
+mod tests {
+    // Test the diagnostics for invalid role definitions
+    #[test]
+    fn test_invalid_roles() {
+        let cases = trybuild::TestCases::new();
+        cases.compile_fail("src/tests/ui/roles/*.rs");
+    }
+    
+    // Test the diagnostics for malformed requirement expressions
+    #[test]
+    fn test_malformed_requirements() {
+        let cases = trybuild::TestCases::new();
+        cases.compile_fail("src/tests/ui/requirements/*.rs");
+    }
+    
+    // Test that a handler parameter named `User` must be rocket_roles' own
+    #[test]
+    fn test_foreign_user_type() {
+        let cases = trybuild::TestCases::new();
+        cases.compile_fail("src/tests/ui/handlers/*.rs");
+    }
+    
+    // Test stacking attributes on one handler, next to an application type
+    // named `User`
+    #[test]
+    fn test_stacked_attributes() {
+        let cases = trybuild::TestCases::new();
+        cases.pass("src/tests/ui/stacking/*.rs");
+    }
+    
+    // Test the names checked against define_roles! with the check-names feature
+    #[cfg(feature = "check-names")]
+    #[test]
+    fn test_undeclared_names() {
+        let cases = trybuild::TestCases::new();
+        cases.compile_fail("src/tests/ui/check_names/*.rs");
+    }
+    
+    // Test that routes are not checked in crates without the check-names
+    // feature, whatever other crates in the build enable
+    #[cfg(not(feature = "check-names"))]
+    #[test]
+    fn test_unchecked_names() {
+        let cases = trybuild::TestCases::new();
+        cases.pass("src/tests/ui/unchecked/*.rs");
+    }
+}